@@ -0,0 +1,297 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    board::QUILT_SIZE,
+    patches::Patch,
+    polyomino::Orientation,
+};
+
+// 9x9 拼布板的 81 位 bitboard，第 row * 9 + col 位表示 (col, row)
+// 序列化成 9 行 '#' / '.'，json 里没有 u128，也方便看
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize, Debug)]
+#[serde(into = "Vec<String>", try_from = "Vec<String>")]
+pub struct Quilt(pub u128);
+
+const FULL: u128 = (1 << (QUILT_SIZE * QUILT_SIZE)) - 1;
+
+// 第 0 列 / 第 8 列的格子，左右移位的时候挡住换行
+const COL_FIRST: u128 = {
+    let mut m = 0;
+    let mut row = 0;
+    while row < QUILT_SIZE {
+        m |= 1 << (row * QUILT_SIZE);
+        row += 1;
+    }
+    m
+};
+const COL_LAST: u128 = COL_FIRST << (QUILT_SIZE - 1);
+
+pub fn bit(col: usize, row: usize) -> u128 {
+    1 << (row * QUILT_SIZE + col)
+}
+
+// 一组格子的掩码，有越界的格子就是 None
+pub fn mask_of(cells: impl IntoIterator<Item = (isize, isize)>) -> Option<u128> {
+    let n = QUILT_SIZE as isize;
+    cells.into_iter().try_fold(0, |m, (x, y)| {
+        if x < 0 || x >= n || y < 0 || y >= n {
+            None
+        } else {
+            Some(m | bit(x as usize, y as usize))
+        }
+    })
+}
+
+// 每个格子往 上 下 左 右 扩一格
+fn spread(m: u128) -> u128 {
+    (m >> QUILT_SIZE) | ((m << QUILT_SIZE) & FULL) | ((m << 1) & !COL_FIRST) | ((m >> 1) & !COL_LAST)
+}
+
+// 左上角的 size x size 正方形
+fn square_mask(size: usize) -> u128 {
+    mask_of((0..size).flat_map(|x| (0..size).map(move |y| (x as isize, y as isize))))
+        .expect("square fits in quilt")
+}
+
+impl Quilt {
+    pub fn is_set(&self, col: usize, row: usize) -> bool {
+        self.0 & bit(col, row) != 0
+    }
+
+    pub fn set(&mut self, col: usize, row: usize) {
+        self.0 |= bit(col, row);
+    }
+
+    pub fn fits(&self, mask: u128) -> bool {
+        self.0 & mask == 0
+    }
+
+    pub fn place(&mut self, mask: u128) {
+        self.0 |= mask;
+    }
+
+    pub fn filled(&self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    pub fn empty(&self) -> usize {
+        (!self.0 & FULL).count_ones() as usize
+    }
+
+    // 空格往 上 下 左 右 看一格，邻格也是空的那些空格
+    fn empty_neighbours(&self) -> [u128; 4] {
+        let e = !self.0 & FULL;
+        [
+            e & (e >> QUILT_SIZE),
+            e & (e << QUILT_SIZE) & FULL,
+            e & (e << 1) & !COL_FIRST,
+            e & (e >> 1) & !COL_LAST,
+        ]
+    }
+
+    // 空格连成的一块块空地
+    pub fn empty_regions(&self) -> Vec<u128> {
+        let mut rest = !self.0 & FULL;
+        let mut regions = vec![];
+        while rest != 0 {
+            // 从最低位的空格开始往四周长
+            let mut region = rest & rest.wrapping_neg();
+            loop {
+                let grown = (region | spread(region)) & rest;
+                if grown == region {
+                    break;
+                }
+                region = grown;
+            }
+            regions.push(region);
+            rest &= !region;
+        }
+        regions
+    }
+
+    // 四周都被占了的空格，基本只能靠 1x1 特殊布填
+    pub fn isolated(&self) -> usize {
+        let e = !self.0 & FULL;
+        let [up, down, left, right] = self.empty_neighbours();
+        (e & !(up | down | left | right)).count_ones() as usize
+    }
+
+    // 空格和已占格子、边框之间的边数，越大空地越碎
+    pub fn perimeter(&self) -> usize {
+        let e = (!self.0 & FULL).count_ones() as usize;
+        4 * e
+            - self
+                .empty_neighbours()
+                .iter()
+                .map(|m| m.count_ones() as usize)
+                .sum::<usize>()
+    }
+
+    // 所有 size x size 正方形里填得最满的格数，看 7x7 的进度
+    pub fn best_square_fill(&self, size: usize) -> usize {
+        let square = square_mask(size);
+        (0..=QUILT_SIZE - size)
+            .flat_map(|row| (0..=QUILT_SIZE - size).map(move |col| (col, row)))
+            .map(|(col, row)| {
+                let m = square << (row * QUILT_SIZE + col);
+                (self.0 & m).count_ones() as usize
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    // 是否有填满的 size x size 正方形
+    pub fn has_full_square(&self, size: usize) -> bool {
+        let square = square_mask(size);
+        (0..=QUILT_SIZE - size).any(|row| {
+            (0..=QUILT_SIZE - size).any(|col| {
+                let m = square << (row * QUILT_SIZE + col);
+                self.0 & m == m
+            })
+        })
+    }
+}
+
+impl From<Quilt> for Vec<String> {
+    fn from(q: Quilt) -> Self {
+        (0..QUILT_SIZE)
+            .map(|row| {
+                (0..QUILT_SIZE)
+                    .map(|col| if q.is_set(col, row) { '#' } else { '.' })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+impl TryFrom<Vec<String>> for Quilt {
+    type Error = String;
+    fn try_from(rows: Vec<String>) -> Result<Self, Self::Error> {
+        if rows.len() != QUILT_SIZE {
+            return Err(format!("quilt needs {} rows, found {}", QUILT_SIZE, rows.len()));
+        }
+        let mut q = Quilt::default();
+        for (row, line) in rows.iter().enumerate() {
+            if line.chars().count() != QUILT_SIZE {
+                return Err(format!("quilt row {} needs {} squares", row, QUILT_SIZE));
+            }
+            for (col, c) in line.chars().enumerate() {
+                match c {
+                    '#' => q.set(col, row),
+                    '.' => {}
+                    c => return Err(format!("bad char in quilt: {:?}", c)),
+                }
+            }
+        }
+        Ok(q)
+    }
+}
+
+// 一块拼布某个朝向、某个锚点的摆放
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct PlacementMask {
+    pub orient: Orientation,
+    // (col, row)
+    pub offset: (usize, usize),
+    pub mask: u128,
+}
+
+// 一块拼布所有朝向 x 锚点的掩码，开局算一次
+#[derive(Clone, Debug)]
+pub struct PatchMasks {
+    // [朝向 * 81 + row * 9 + col]，越界是 None
+    all: Vec<Option<u128>>,
+    // 去掉对称重复朝向之后放得进拼布板的摆放
+    pub distinct: Vec<PlacementMask>,
+}
+
+impl PatchMasks {
+    pub fn new(patch: &Patch) -> Self {
+        let n = QUILT_SIZE * QUILT_SIZE;
+        let mut all = vec![None; 8 * n];
+        for o in Orientation::all() {
+            for row in 0..QUILT_SIZE {
+                for col in 0..QUILT_SIZE {
+                    let cells = patch.get_pos((col as isize, row as isize), o);
+                    all[o.index() * n + row * QUILT_SIZE + col] = mask_of(cells);
+                }
+            }
+        }
+
+        let mut distinct = vec![];
+        for o in patch.shape.distinct_orientations() {
+            for row in 0..QUILT_SIZE {
+                for col in 0..QUILT_SIZE {
+                    if let Some(mask) = all[o.index() * n + row * QUILT_SIZE + col] {
+                        distinct.push(PlacementMask {
+                            orient: o,
+                            offset: (col, row),
+                            mask,
+                        });
+                    }
+                }
+            }
+        }
+        Self { all, distinct }
+    }
+
+    pub fn get(&self, offset: (usize, usize), orient: Orientation) -> Option<u128> {
+        if offset.0 >= QUILT_SIZE || offset.1 >= QUILT_SIZE {
+            return None;
+        }
+        let n = QUILT_SIZE * QUILT_SIZE;
+        self.all[orient.index() * n + offset.1 * QUILT_SIZE + offset.0]
+    }
+
+    // 在这块拼布板上所有合法的摆放
+    pub fn legal<'a>(&'a self, quilt: &'a Quilt) -> impl Iterator<Item = &'a PlacementMask> + 'a {
+        self.distinct.iter().filter(|p| quilt.fits(p.mask))
+    }
+}
+
+#[test]
+fn test_bitboard() {
+    use crate::polyomino::Polyomino;
+
+    let mut q = Quilt::default();
+    assert_eq!(q.empty(), 81);
+    q.set(8, 8);
+    assert!(q.is_set(8, 8));
+    assert_eq!((q.filled(), q.empty()), (1, 80));
+
+    // 1x3 横竖两个朝向，各 7 * 9 个位置
+    let patch = Patch::new(Polyomino::from_rows(&["###"]).unwrap());
+    let masks = PatchMasks::new(&patch);
+    assert_eq!(masks.distinct.len(), 2 * 7 * 9);
+    assert_eq!(masks.get((7, 0), Orientation::default()), None);
+    assert_eq!(
+        masks.get((6, 0), Orientation::default()),
+        Some(bit(6, 0) | bit(7, 0) | bit(8, 0))
+    );
+    // (8, 8) 被占之后少了横竖各一个摆放
+    assert_eq!(masks.legal(&q).count(), 2 * 7 * 9 - 2);
+
+    for x in 1..8 {
+        for y in 2..9 {
+            q.set(x, y);
+        }
+    }
+    assert!(q.has_full_square(7));
+    assert!(!q.has_full_square(8));
+    assert_eq!(q.best_square_fill(7), 49);
+
+    // 空板: 周长就是边框，没有孤立的空格
+    let mut q = Quilt::default();
+    assert_eq!((q.perimeter(), q.isolated()), (36, 0));
+    // 围住 (1, 1)，左上角 (0, 0) 也跟着被围住了
+    for (x, y) in [(0, 1), (2, 1), (1, 0), (1, 2)] {
+        q.set(x, y);
+    }
+    assert_eq!(q.isolated(), 2);
+    q.set(0, 0);
+    assert_eq!(q.isolated(), 1);
+    let regions = q.empty_regions();
+    assert_eq!(regions.len(), 2);
+    assert_eq!(regions.iter().map(|r| r.count_ones()).min(), Some(1));
+    assert_eq!(q.best_square_fill(7), 5);
+}
@@ -0,0 +1,662 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bitboard::{PatchMasks, PlacementMask, Quilt},
+    patches::{Patch, new_patches, setup_patches},
+    polyomino::Orientation,
+    rng::GameRng,
+};
+
+// 拼布板的边长
+pub const QUILT_SIZE: usize = 9;
+
+// 时间板终点 两个玩家都走到53游戏结束
+pub const TIME_TRACK_END: usize = 53;
+
+// 中立指示物前面可以买的拼布数
+pub const MARKET_SIZE: usize = 3;
+
+// 时间板上 1x1 特殊布的位置，从后往前拿
+pub const SPECIAL_PATCH_POS: [usize; 5] = [49, 43, 31, 25, 19];
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub enum BoardType {
+    // Yellow,
+    Blue,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub enum TimeBoardType {
+    // Circle,
+    Square,
+}
+
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub struct Player {
+    // 玩家存款 5元
+    pub money: usize,
+    // 玩家指示物 位置
+    pub pos_idx: usize,
+    // move tick 表示在哪个tick执行了移动
+    pub last_move_tick: usize,
+    // 拼布板上所有拼布的纽扣收入之和
+    pub button_income: usize,
+    // 自己拼布板上被占据的格子
+    pub quilt: Quilt,
+}
+
+impl Player {
+    fn new(last_move_tick: usize) -> Self {
+        Self {
+            money: 5,
+            pos_idx: 0,
+            last_move_tick,
+            button_income: 0,
+            quilt: Quilt::default(),
+        }
+    }
+
+    // (col, row) 越界或已被占
+    pub fn is_taken(&self, x: isize, y: isize) -> bool {
+        !in_quilt(x, y) || self.quilt.is_set(x as usize, y as usize)
+    }
+}
+
+// 拼布放在了谁的拼布板的哪里
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub struct Placement {
+    pub player: usize,
+    // (col, row)
+    pub offset: (usize, usize),
+    pub orient: Orientation,
+}
+
+// 行动带来的结算，前端拿来做提示
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GameEvent {
+    // 选择前进拿到的纽扣
+    Advanced { player: usize, buttons: usize },
+    // 经过纽扣格拿到的收入
+    ButtonIncome { player: usize, buttons: usize },
+    // 第一个经过特殊布格子，拿到 1x1 的皮革拼布
+    SpecialPatch { player: usize },
+    // 第一个填满 7x7，拿到 7x7 板块
+    BonusTile { player: usize },
+}
+
+// 行动失败的原因
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MoveError {
+    // idx 超出范围
+    OutOfRange { idx: usize, len: usize },
+    // 已经放置过
+    AlreadyPut(usize),
+    // 不是中立指示物前面的三块
+    NotInMarket(usize),
+    // 越界或和已有拼布交叉
+    Cross(usize),
+    // 格子越界或已经被占
+    SquareTaken { col: usize, row: usize },
+    // 纽扣不够
+    NotEnoughMoney { need: usize, have: usize },
+    // 要先放下拿到的 1x1 特殊布
+    SpecialPending,
+    // 没有要放的特殊布
+    NoSpecialPending,
+    // 游戏已经结束
+    GameOver,
+}
+
+#[derive(Clone, Debug)]
+pub struct BoardGame {
+    // 每个玩家的拼布图版的样式
+    pub board_type: BoardType,
+
+    // 中央时间板的样式
+    pub time_board_type: TimeBoardType,
+
+    // 中央银行的存款 32 + 12 * 5 + 5 * 10 + 1 * 20
+    pub bank_money: usize,
+
+    // player // 53的位置结束
+    pub players: [Player; 2],
+
+    // 标识当前move的 tick
+    pub global_move_tick: usize,
+
+    // 当前行动的玩家 idx
+    // player: usize,
+
+    // 特殊布的位置 19 25 31 43 49 反着放 pop尾部
+    pub special_patches: Vec<usize>,
+    // 拿到了还没放下的特殊布，对应的玩家 idx
+    pub pending_special: Vec<usize>,
+    // 已经放下的特殊布 (玩家, (col, row))
+    pub special_pos: Vec<(usize, (usize, usize))>,
+
+    // 棋盘上纽扣的位置 4 10 16 22 28 34 40 46 52
+    pub button_pos: [usize; 9],
+
+    // 开局洗牌用的 seed，同一个 seed 得到同一局
+    pub seed: u64,
+
+    // 拼布的随机初始化 按顺时针围成一圈
+    // 开局之后不再变，clone 的时候共享
+    pub patches: Arc<Vec<Patch>>,
+    // 每块拼布所有摆放的掩码
+    pub masks: Arc<Vec<PatchMasks>>,
+    // 中立指示物所在的位置，从这里顺时针数三块可以买
+    pub neutral: usize,
+    // 拼布放置的位置
+    pub patch_pos: Vec<Option<Placement>>,
+    // 77 板块 拿到的玩家
+    pub bonus_owner: Option<usize>,
+}
+
+impl BoardGame {
+    // 买下拼布 idx 放到当前玩家的拼布板上 offset: (col, row)
+    pub fn put(
+        &mut self,
+        idx: usize,
+        offset: (usize, usize),
+        orient: Orientation,
+    ) -> Result<Vec<GameEvent>, MoveError> {
+        self.check_put(idx, offset, orient)?;
+        let player = self.current_player();
+        let (cost, time) = self.patches[idx].bt;
+
+        // 玩家付钱给中央银行，记下这块拼布的纽扣收入
+        self.players[player].money -= cost;
+        self.players[player].button_income += self.patches[idx].button;
+        self.bank_money += cost;
+
+        // 中立指示物移到买走的拼布的位置
+        self.neutral = idx;
+
+        // 拼布放置的位置更新
+        self.patch_pos[idx] = Some(Placement {
+            player,
+            offset,
+            orient,
+        });
+        // 占据的格子的更新，check_put 已经确认掩码存在
+        // 要在移动之前放，经过特殊布时才知道拼布板还有没有空格
+        if let Some(mask) = self.masks[idx].get(offset, orient) {
+            self.players[player].quilt.place(mask);
+        }
+
+        // 按时间花费移动当前玩家
+        let mut events = self.move_player(player, time);
+        events.extend(self.check_bonus(player));
+        Ok(events)
+    }
+
+    pub fn can_put(&self, idx: usize, offset: (usize, usize), orient: Orientation) -> bool {
+        self.check_put(idx, offset, orient).is_ok()
+    }
+
+    pub fn check_put(
+        &self,
+        idx: usize,
+        offset: (usize, usize),
+        orient: Orientation,
+    ) -> Result<(), MoveError> {
+        if self.is_game_over() {
+            return Err(MoveError::GameOver);
+        }
+        if !self.pending_special.is_empty() {
+            return Err(MoveError::SpecialPending);
+        }
+
+        // 校验 idx 范围
+        if idx >= self.patches.len() {
+            return Err(MoveError::OutOfRange {
+                idx,
+                len: self.patches.len(),
+            });
+        }
+
+        // 校验是否放置
+        if self.patch_pos[idx].is_some() {
+            return Err(MoveError::AlreadyPut(idx));
+        }
+
+        // 校验是不是中立指示物前面的三块
+        if !self.market_choices().contains(&idx) {
+            return Err(MoveError::NotInMarket(idx));
+        }
+
+        // 校验买不买得起
+        let player = &self.players[self.current_player()];
+        let need = self.patches[idx].bt.0;
+        let have = player.money;
+        if need > have {
+            return Err(MoveError::NotEnoughMoney { need, have });
+        }
+
+        // 校验交叉
+        match self.masks[idx].get(offset, orient) {
+            Some(mask) if player.quilt.fits(mask) => Ok(()),
+            _ => Err(MoveError::Cross(idx)),
+        }
+    }
+
+    // 拼布在玩家拼布板上所有合法的摆放（已去掉对称重复的朝向）
+    pub fn placements(&self, idx: usize, player: usize) -> impl Iterator<Item = &PlacementMask> {
+        self.masks[idx].legal(&self.players[player].quilt)
+    }
+
+    // 中立指示物顺时针往后还没被买的三块
+    pub fn market_choices(&self) -> Vec<usize> {
+        let n = self.patches.len();
+        (0..n)
+            .map(|i| (self.neutral + i) % n)
+            .filter(|&idx| self.patch_pos[idx].is_none())
+            .take(MARKET_SIZE)
+            .collect()
+    }
+
+    // 把拿到的 1x1 特殊布放到 (col, row)
+    pub fn put_special(&mut self, offset: (usize, usize)) -> Result<Vec<GameEvent>, MoveError> {
+        self.check_put_special(offset)?;
+        let player = self.pending_special.remove(0);
+        self.players[player].quilt.set(offset.0, offset.1);
+        self.special_pos.push((player, offset));
+        Ok(self.check_bonus(player).into_iter().collect())
+    }
+
+    pub fn check_put_special(&self, offset: (usize, usize)) -> Result<(), MoveError> {
+        let Some(&player) = self.pending_special.first() else {
+            return Err(MoveError::NoSpecialPending);
+        };
+        if self.players[player].is_taken(offset.0 as isize, offset.1 as isize) {
+            return Err(MoveError::SquareTaken {
+                col: offset.0,
+                row: offset.1,
+            });
+        }
+        Ok(())
+    }
+
+    // 当前行动的玩家: 有没放的特殊布的先放
+    // 否则落后的先走，同一格则后到的先走
+    pub fn current_player(&self) -> usize {
+        if let Some(&player) = self.pending_special.first() {
+            return player;
+        }
+        let [p0, p1] = &self.players;
+        if p0.pos_idx != p1.pos_idx {
+            if p0.pos_idx < p1.pos_idx { 0 } else { 1 }
+        } else if p0.last_move_tick > p1.last_move_tick {
+            0
+        } else {
+            1
+        }
+    }
+
+    // 移动玩家指示物，最多走到终点
+    // 经过纽扣格时按拼布板上的纽扣收入从银行领钱
+    pub fn move_player(&mut self, player: usize, steps: usize) -> Vec<GameEvent> {
+        let from = self.players[player].pos_idx;
+        let to = (from + steps).min(TIME_TRACK_END);
+
+        let p = &mut self.players[player];
+        p.pos_idx = to;
+        p.last_move_tick = self.global_move_tick;
+        self.global_move_tick += 1;
+
+        let mut events = vec![];
+        let crossed = self
+            .button_pos
+            .iter()
+            .filter(|&&b| from < b && b <= to)
+            .count();
+        for _ in 0..crossed {
+            let buttons = self.pay(player, self.players[player].button_income);
+            if buttons > 0 {
+                events.push(GameEvent::ButtonIncome { player, buttons });
+            }
+        }
+
+        // 特殊布 谁先经过就给谁
+        // 拼布板上已经没有空格放了就直接拿走作废，不然没有合法行动，游戏会卡住
+        while let Some(&pos) = self.special_patches.last() {
+            if pos > to {
+                break;
+            }
+            self.special_patches.pop();
+            let pending = self.pending_special.iter().filter(|&&p| p == player).count();
+            if self.players[player].quilt.empty() <= pending {
+                continue;
+            }
+            self.pending_special.push(player);
+            events.push(GameEvent::SpecialPatch { player });
+        }
+        events
+    }
+
+    // 银行付钱给玩家，银行没钱了就只付剩下的
+    fn pay(&mut self, player: usize, buttons: usize) -> usize {
+        let paid = buttons.min(self.bank_money);
+        self.bank_money -= paid;
+        self.players[player].money += paid;
+        paid
+    }
+
+    // 前进到对手前一格，每走一格从银行拿一个纽扣
+    pub fn advance(&mut self) -> Result<Vec<GameEvent>, MoveError> {
+        if self.is_game_over() {
+            return Err(MoveError::GameOver);
+        }
+        if !self.pending_special.is_empty() {
+            return Err(MoveError::SpecialPending);
+        }
+        let player = self.current_player();
+        let steps = self.advance_steps(player);
+
+        let buttons = self.pay(player, steps);
+        let mut events = vec![GameEvent::Advanced { player, buttons }];
+        events.extend(self.move_player(player, steps));
+        Ok(events)
+    }
+
+    // 选择前进时要走的格数
+    pub fn advance_steps(&self, player: usize) -> usize {
+        let target = (self.players[1 - player].pos_idx + 1).min(TIME_TRACK_END);
+        target.saturating_sub(self.players[player].pos_idx)
+    }
+
+    // 两个玩家都到终点，并且特殊布都放完了
+    pub fn is_game_over(&self) -> bool {
+        self.players.iter().all(|p| p.pos_idx >= TIME_TRACK_END) && self.pending_special.is_empty()
+    }
+
+    // 按 seed 洗牌开一局
+    pub fn new(seed: u64) -> Self {
+        Self::from_catalogue(new_patches(), seed)
+    }
+
+    // 用外部读进来的拼布目录按 seed 洗牌开一局
+    pub fn from_catalogue(patches: Vec<Patch>, seed: u64) -> Self {
+        let patches = setup_patches(patches, &mut GameRng::new(seed));
+        Self::with_patches(patches, seed)
+    }
+
+    // 用摆好的拼布开一局，不再洗牌
+    pub fn with_patches(patches: Vec<Patch>, seed: u64) -> Self {
+        let masks = patches.iter().map(PatchMasks::new).collect();
+        Self {
+            board_type: BoardType::Blue,
+            time_board_type: TimeBoardType::Square,
+            bank_money: 32 + 12 * 5 + 5 * 10 + 20,
+            players: [Player::new(1), Player::new(0)],
+            global_move_tick: 2, // move_tick从2开始计数 谁移动了，谁的last move_tick就设置成global move_tick，之后global_move + 1
+            special_patches: SPECIAL_PATCH_POS.to_vec(),
+            pending_special: vec![],
+            special_pos: vec![],
+            button_pos: [4, 10, 16, 22, 28, 34, 40, 46, 52],
+            seed,
+            patch_pos: vec![None; patches.len()],
+            masks: Arc::new(masks),
+            neutral: 0,
+            bonus_owner: None,
+            patches: Arc::new(patches),
+        }
+    }
+}
+
+impl Default for BoardGame {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+fn in_quilt(x: isize, y: isize) -> bool {
+    x >= 0 && x < QUILT_SIZE as isize && y >= 0 && y < QUILT_SIZE as isize
+}
+
+#[test]
+fn test_time_track() {
+    let mut game = BoardGame::with_patches(new_patches(), 0);
+    // 不考虑特殊布
+    game.special_patches.clear();
+    // 开局0号先走
+    assert_eq!(game.current_player(), 0);
+
+    // 0号走到3，落后的1号接着走
+    game.move_player(0, 3);
+    assert_eq!(game.current_player(), 1);
+
+    // 1号也到3，后到的1号继续走
+    game.move_player(1, 3);
+    assert_eq!(game.current_player(), 1);
+
+    game.move_player(1, 1);
+    assert_eq!(game.current_player(), 0);
+
+    // 不会超过终点
+    game.move_player(0, 100);
+    assert_eq!(game.players[0].pos_idx, TIME_TRACK_END);
+    assert!(!game.is_game_over());
+    game.move_player(1, 100);
+    assert!(game.is_game_over());
+}
+
+#[test]
+fn test_advance() {
+    let mut game = BoardGame::with_patches(new_patches(), 0);
+    let bank = game.bank_money;
+
+    // 同在0格，前进到1格拿1个纽扣
+    assert_eq!(
+        game.advance(),
+        Ok(vec![GameEvent::Advanced {
+            player: 0,
+            buttons: 1
+        }])
+    );
+    assert_eq!(game.players[0].pos_idx, 1);
+    assert_eq!(game.players[0].money, 6);
+    assert_eq!(game.bank_money, bank - 1);
+
+    // 1号落后，走到2格
+    assert_eq!(game.current_player(), 1);
+    assert_eq!(
+        game.advance(),
+        Ok(vec![GameEvent::Advanced {
+            player: 1,
+            buttons: 2
+        }])
+    );
+    assert_eq!(game.players[1].pos_idx, 2);
+    assert_eq!(game.players[1].money, 7);
+}
+
+#[test]
+fn test_put_cost() {
+    let mut game = BoardGame::with_patches(new_patches(), 0);
+    let bank = game.bank_money;
+
+    // 0号: 花费 (10, 4) 的拼布买不起
+    assert_eq!(
+        game.check_put(2, (0, 0), Orientation::default()),
+        Err(MoveError::NotEnoughMoney { need: 10, have: 5 })
+    );
+
+    // 不在市场里的拼布不能买，也不扣钱
+    assert_eq!(
+        game.put(4, (0, 0), Orientation::default()),
+        Err(MoveError::NotInMarket(4))
+    );
+    assert_eq!(game.players[0].money, 5);
+    assert_eq!(game.players[0].pos_idx, 0);
+
+    // 花费 (1, 2) 收入 0
+    game.put(0, (0, 0), Orientation::default()).unwrap();
+    assert_eq!(game.players[0].money, 4);
+    assert_eq!(game.players[0].button_income, 0);
+    assert_eq!(game.players[0].pos_idx, 2);
+    assert_eq!(game.bank_money, bank + 1);
+
+    // 1号: 花费 (3, 4) 收入 1，走到 4 正好经过纽扣格领 1 个
+    assert_eq!(game.current_player(), 1);
+    game.put(3, (0, 0), Orientation::default()).unwrap();
+    assert_eq!(game.players[1].money, 3);
+    assert_eq!(game.players[1].button_income, 1);
+    assert_eq!(game.players[1].pos_idx, 4);
+    assert_eq!(game.bank_money, bank + 3);
+}
+
+#[test]
+fn test_button_income() {
+    let mut game = BoardGame::with_patches(new_patches(), 0);
+    game.players[0].button_income = 3;
+    let bank = game.bank_money;
+
+    // 0 -> 3 没经过纽扣格
+    assert!(game.move_player(0, 3).is_empty());
+
+    // 3 -> 10 经过 4 和 10
+    let events = game.move_player(0, 7);
+    assert_eq!(
+        events,
+        vec![GameEvent::ButtonIncome {
+            player: 0,
+            buttons: 3
+        }; 2]
+    );
+    assert_eq!(game.players[0].money, 5 + 6);
+    assert_eq!(game.bank_money, bank - 6);
+}
+
+#[test]
+fn test_special_patch() {
+    let mut game = BoardGame::with_patches(new_patches(), 0);
+    game.move_player(1, 18);
+
+    // 0号经过 19，拿到特殊布
+    let events = game.move_player(0, 20);
+    assert_eq!(events, vec![GameEvent::SpecialPatch { player: 0 }]);
+    assert_eq!(game.special_patches, vec![49, 43, 31, 25]);
+
+    // 虽然1号落后，还是0号先放特殊布
+    assert_eq!(game.current_player(), 0);
+    assert_eq!(game.advance(), Err(MoveError::SpecialPending));
+    assert_eq!(game.put_special((4, 4)), Ok(vec![]));
+    assert!(game.players[0].quilt.is_set(4, 4));
+    assert!(!game.players[1].quilt.is_set(4, 4));
+    assert_eq!(game.current_player(), 1);
+
+    // 1号再经过 19 就没有了
+    assert!(game.move_player(1, 3).is_empty());
+    assert_eq!(game.put_special((4, 5)), Err(MoveError::NoSpecialPending));
+}
+
+#[test]
+fn test_special_patch_full_quilt() {
+    let mut game = BoardGame::with_patches(new_patches(), 0);
+
+    // 0号拼布板只剩一个空格，一次经过 19 和 25 两块特殊布，只拿得到一块
+    for x in 0..9 {
+        for y in 0..9 {
+            if (x, y) != (8, 8) {
+                game.players[0].quilt.set(x, y);
+            }
+        }
+    }
+    let events = game.move_player(0, 26);
+    assert_eq!(events, vec![GameEvent::SpecialPatch { player: 0 }]);
+    assert_eq!(game.pending_special, vec![0]);
+    assert_eq!(game.special_patches, vec![49, 43, 31]);
+    game.put_special((8, 8)).unwrap();
+
+    // 拼布板满了，再经过的特殊布作废，还是有合法行动
+    game.move_player(0, 10);
+    assert!(game.pending_special.is_empty());
+    assert_eq!(game.special_patches, vec![49, 43]);
+    assert!(!game.legal_moves().is_empty());
+}
+
+#[test]
+fn test_put_fills_quilt_before_special_patch() {
+    use crate::moves::Move;
+
+    let mut game = BoardGame::with_patches(new_patches(), 0);
+    // 0号拼布板只剩 (0,0) (1,0)，在 18，买 2x1 走一格正好经过 19
+    for x in 0..9 {
+        for y in 0..9 {
+            if y != 0 || x >= 2 {
+                game.players[0].quilt.set(x, y);
+            }
+        }
+    }
+    game.players[0].pos_idx = 18;
+    game.players[1].pos_idx = 20;
+    game.neutral = 9;
+    assert_eq!(game.current_player(), 0);
+
+    let events = game
+        .play(&Move::Put {
+            idx: 9,
+            offset: (0, 0),
+            orient: Orientation::default(),
+        })
+        .unwrap();
+    // 放满了，19 的特殊布作废
+    assert_eq!(events, vec![GameEvent::BonusTile { player: 0 }]);
+    assert!(game.pending_special.is_empty());
+    assert_eq!(game.special_patches, vec![49, 43, 31, 25]);
+    assert_eq!(game.players[0].quilt.empty(), 0);
+    assert!(!game.legal_moves().is_empty());
+}
+
+#[test]
+fn test_quilt_per_player() {
+    let mut game = BoardGame::with_patches(new_patches(), 0);
+
+    // 0号放在自己的拼布板上
+    game.put(0, (0, 0), Orientation::default()).unwrap();
+    assert!(game.players[0].quilt.is_set(0, 0));
+    assert!(!game.players[1].quilt.is_set(0, 0));
+
+    // 1号的拼布板同一个位置还能放
+    assert_eq!(game.current_player(), 1);
+    assert!(game.can_put(3, (0, 0), Orientation::default()));
+    game.put(3, (0, 0), Orientation::default()).unwrap();
+    assert_eq!(game.patch_pos[3].map(|p| p.player), Some(1));
+    assert!(game.players[1].quilt.is_set(0, 0));
+}
+
+#[test]
+fn test_market() {
+    let mut game = BoardGame::with_patches(new_patches(), 0);
+    assert_eq!(game.market_choices(), vec![0, 1, 2]);
+    assert_eq!(
+        game.check_put(4, (0, 0), Orientation::default()),
+        Err(MoveError::NotInMarket(4))
+    );
+
+    // 买走1号位，指示物跳过去
+    game.put(1, (0, 0), Orientation::default()).unwrap();
+    assert_eq!(game.neutral, 1);
+    assert_eq!(game.market_choices(), vec![2, 3, 4]);
+
+    // 绕一圈回到开头，跳过已经买走的
+    game.neutral = game.patches.len() - 1;
+    assert_eq!(game.market_choices(), vec![32, 0, 2]);
+}
+
+#[test]
+fn test_seeded_setup() {
+    let a = BoardGame::new(7);
+    let b = BoardGame::new(7);
+    let ids = |g: &BoardGame| g.patches.iter().map(|p| p.id).collect::<Vec<_>>();
+    assert_eq!(ids(&a), ids(&b));
+    assert_ne!(ids(&a), ids(&BoardGame::new(8)));
+
+    // 2x1 在最后，第一轮最后才能买到
+    assert_eq!(a.patches.last().map(|p| p.area()), Some(2));
+    assert!(!a.market_choices().contains(&(a.patches.len() - 1)));
+}
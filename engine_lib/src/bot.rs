@@ -0,0 +1,187 @@
+use crate::{
+    bitboard::Quilt,
+    board::{BoardGame, TIME_TRACK_END},
+    moves::Move,
+    score::{BONUS_SIZE, BONUS_TILE_SCORE},
+    solver::SolverWeights,
+};
+
+// 电脑玩家: 轮到它的时候给一个行动
+pub trait Bot {
+    fn name(&self) -> &str;
+
+    // 没有合法行动 (游戏结束) 时是 None
+    fn choose(&mut self, game: &BoardGame) -> Option<Move>;
+}
+
+// 局面估值的权重，单位都是纽扣
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Heuristic {
+    // 剩下每一次纽扣收入
+    pub income: f64,
+    // 时间板上剩下的每一格 (前进可以一格换一个纽扣)
+    pub time: f64,
+    // 拼布板上每个空格
+    pub empty: f64,
+    // 被围死的空格
+    pub isolated: f64,
+    // 空地的周长，越碎越难放
+    pub perimeter: f64,
+    // 7x7 还没人拿的时候，最满的 7x7 每填一格
+    pub bonus_race: f64,
+}
+
+impl Default for Heuristic {
+    fn default() -> Self {
+        Self {
+            income: 1.0,
+            time: 1.0,
+            empty: 2.0,
+            isolated: 1.5,
+            perimeter: 0.1,
+            bonus_race: 0.1,
+        }
+    }
+}
+
+impl Heuristic {
+    // 站在 player 的角度给局面估个价: 手里的 + 以后能拿的 - 终局要扣的
+    pub fn value(&self, game: &BoardGame, player: usize) -> f64 {
+        let p = &game.players[player];
+        let incomes_left = game.button_pos.iter().filter(|&&b| b > p.pos_idx).count();
+        let time_left = TIME_TRACK_END - p.pos_idx;
+        // 拿到还没放的特殊布，等于马上少一个空格
+        let specials = game.pending_special.iter().filter(|&&s| s == player).count();
+        let empty = p.quilt.empty().saturating_sub(specials);
+
+        let bonus = match game.bonus_owner {
+            Some(owner) if owner == player => BONUS_TILE_SCORE as f64,
+            _ => 0.0,
+        };
+        p.money as f64
+            + self.income * (p.button_income * incomes_left) as f64
+            + self.time * time_left as f64
+            - self.empty * empty as f64
+            + self.quilt_value(&p.quilt, game.bonus_owner.is_none())
+            + bonus
+    }
+
+    // 只看拼布板形状的那部分: 碎不碎，7x7 还在抢的时候进度如何
+    pub fn quilt_value(&self, quilt: &Quilt, bonus_open: bool) -> f64 {
+        let race = if bonus_open {
+            self.bonus_race * quilt.best_square_fill(BONUS_SIZE) as f64
+        } else {
+            0.0
+        };
+        race - self.isolated * quilt.isolated() as f64 - self.perimeter * quilt.perimeter() as f64
+    }
+
+    // 行动前后当前玩家估值的差
+    pub fn evaluate(&self, game: &BoardGame, mv: &Move) -> f64 {
+        let player = game.current_player();
+        let mut after = game.clone();
+        if after.play(mv).is_err() {
+            return f64::NEG_INFINITY;
+        }
+        self.value(&after, player) - self.value(game, player)
+    }
+}
+
+// 贪心: 每步挑估值涨得最多的行动，不往后看
+#[derive(Clone, Debug)]
+pub struct GreedyBot {
+    pub heuristic: Heuristic,
+    // 先用求解器挑位置，每块拼布只估前几个摆放
+    pub solver: SolverWeights,
+    pub placements_per_patch: usize,
+}
+
+impl Default for GreedyBot {
+    fn default() -> Self {
+        Self {
+            heuristic: Heuristic::default(),
+            solver: SolverWeights::default(),
+            placements_per_patch: 4,
+        }
+    }
+}
+
+impl GreedyBot {
+    // 候选行动按估值从高到低
+    pub fn ranked(&self, game: &BoardGame) -> Vec<(Move, f64)> {
+        let candidates = if game.pending_special.is_empty() && !game.is_game_over() {
+            let player = game.current_player();
+            let mut moves: Vec<Move> = game
+                .buyable_patches()
+                .into_iter()
+                .flat_map(|idx| {
+                    game.rank_placements(idx, player, &self.solver)
+                        .into_iter()
+                        .take(self.placements_per_patch)
+                        .map(move |r| Move::Put {
+                            idx,
+                            offset: r.offset,
+                            orient: r.orient,
+                        })
+                })
+                .collect();
+            moves.push(Move::Advance);
+            moves
+        } else {
+            game.legal_moves()
+        };
+        let mut moves: Vec<(Move, f64)> = candidates
+            .into_iter()
+            .map(|mv| (mv, self.heuristic.evaluate(game, &mv)))
+            .collect();
+        moves.sort_by(|a, b| b.1.total_cmp(&a.1));
+        moves
+    }
+}
+
+impl Bot for GreedyBot {
+    fn name(&self) -> &str {
+        "greedy"
+    }
+
+    fn choose(&mut self, game: &BoardGame) -> Option<Move> {
+        self.ranked(game).first().map(|&(mv, _)| mv)
+    }
+}
+
+#[test]
+fn test_greedy_bot() {
+    use crate::polyomino::Orientation;
+
+    let mut bot = GreedyBot::default();
+    let game = BoardGame::new(5);
+    let mv = bot.choose(&game).unwrap();
+    assert!(game.check_move(&mv).is_ok());
+
+    // 开局就有收入的便宜拼布比前进好
+    let mut game = BoardGame::new(5);
+    game.players[0].money = 100;
+    let ranked = bot.ranked(&game);
+    assert!(matches!(ranked[0].0, Move::Put { .. }));
+
+    // 放在角落比放在中间好
+    let h = Heuristic::default();
+    let idx = game.buyable_patches()[0];
+    let at = |offset| Move::Put {
+        idx,
+        offset,
+        orient: Orientation::default(),
+    };
+    assert!(h.evaluate(&game, &at((0, 0))) > h.evaluate(&game, &at((3, 3))));
+
+    // 一直自己跟自己下，总能下完
+    let mut game = BoardGame::new(9);
+    let mut plies = 0;
+    while let Some(mv) = bot.choose(&game) {
+        game.play(&mv).unwrap();
+        plies += 1;
+        assert!(plies < 500);
+    }
+    assert!(game.is_game_over());
+    assert!(game.winner().is_some());
+}
@@ -0,0 +1,185 @@
+use std::{fmt, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    patches::Patch,
+    polyomino::{Polyomino, ShapeError},
+};
+
+// 内置的拼布目录，和前端 embedded 的是同一个文件
+pub const DEFAULT_CATALOGUE: &str = include_str!("../../assets/patches.json");
+
+// 单块拼布最大的边长，前端外圈按 5x5 画
+pub const MAX_PATCH_SIZE: usize = 5;
+
+// 数据文件里的一块拼布
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub struct PatchDef {
+    // 按行写: '#' 有格子 '.' 空
+    pub shape: Vec<String>,
+    // 纽扣花费
+    pub cost: usize,
+    // 时间花费
+    pub time: usize,
+    // 纽扣收入
+    #[serde(default)]
+    pub button: usize,
+}
+
+// 拼布目录数据文件
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub struct CatalogueDef {
+    // 拼布总数，用来检查有没有漏写或多写
+    pub total_patches: usize,
+    pub patches: Vec<PatchDef>,
+}
+
+// 某一块拼布的问题，idx 是在文件里的下标
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum PatchProblem {
+    BadShape { idx: usize, err: ShapeError },
+    TooBig { idx: usize, width: usize, height: usize },
+    // 形状和花费、收入都一样
+    Duplicate { first: usize, second: usize },
+    WrongTotal { expected: usize, found: usize },
+    // 开局要放在最后的 2x1 拼布，必须正好一块
+    StartPatch { found: usize },
+}
+
+#[derive(Debug)]
+pub enum CatalogueError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    Invalid(Vec<PatchProblem>),
+}
+
+impl fmt::Display for CatalogueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CatalogueError::Io(e) => write!(f, "read catalogue fail: {}", e),
+            CatalogueError::Json(e) => write!(f, "parse catalogue fail: {}", e),
+            CatalogueError::Invalid(problems) => {
+                write!(f, "invalid catalogue:")?;
+                for p in problems {
+                    write!(f, " {:?};", p)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for CatalogueError {}
+
+impl CatalogueDef {
+    // 校验并转成拼布，有问题就把所有问题一起报出来
+    pub fn build(&self) -> Result<Vec<Patch>, CatalogueError> {
+        let mut problems = vec![];
+        let mut patches: Vec<Patch> = vec![];
+
+        if self.patches.len() != self.total_patches {
+            problems.push(PatchProblem::WrongTotal {
+                expected: self.total_patches,
+                found: self.patches.len(),
+            });
+        }
+
+        for (idx, def) in self.patches.iter().enumerate() {
+            let rows: Vec<&str> = def.shape.iter().map(String::as_str).collect();
+            let shape = match Polyomino::from_rows(&rows) {
+                Ok(shape) => shape,
+                Err(err) => {
+                    problems.push(PatchProblem::BadShape { idx, err });
+                    continue;
+                }
+            };
+            let (width, height) = (shape.width(), shape.height());
+            if width.max(height) > MAX_PATCH_SIZE {
+                problems.push(PatchProblem::TooBig { idx, width, height });
+            }
+
+            let patch = Patch {
+                id: idx,
+                shape,
+                bt: (def.cost, def.time),
+                button: def.button,
+            };
+            if let Some(first) = patches.iter().find(|p| {
+                p.bt == patch.bt && p.button == patch.button && p.shape.same_shape(&patch.shape)
+            }) {
+                problems.push(PatchProblem::Duplicate {
+                    first: first.id,
+                    second: idx,
+                });
+            }
+            patches.push(patch);
+        }
+
+        let found = patches.iter().filter(|p| p.area() == 2).count();
+        if found != 1 {
+            problems.push(PatchProblem::StartPatch { found });
+        }
+
+        if problems.is_empty() {
+            Ok(patches)
+        } else {
+            Err(CatalogueError::Invalid(problems))
+        }
+    }
+}
+
+pub fn parse_catalogue(json: &str) -> Result<Vec<Patch>, CatalogueError> {
+    let def: CatalogueDef = serde_json::from_str(json).map_err(CatalogueError::Json)?;
+    def.build()
+}
+
+// 从文件读拼布目录，改平衡性不用重新编译
+pub fn load_catalogue(path: impl AsRef<Path>) -> Result<Vec<Patch>, CatalogueError> {
+    let json = std::fs::read_to_string(path).map_err(CatalogueError::Io)?;
+    parse_catalogue(&json)
+}
+
+#[test]
+fn test_default_catalogue() {
+    let patches = parse_catalogue(DEFAULT_CATALOGUE).unwrap();
+    assert_eq!(patches.len(), 33);
+    assert!(patches.iter().enumerate().all(|(idx, p)| p.id == idx));
+}
+
+#[test]
+fn test_catalogue_validation() {
+    let json = r#######"{
+        "total_patches": 4,
+        "patches": [
+            { "shape": ["##"], "cost": 2, "time": 1 },
+            { "shape": ["#.#"], "cost": 1, "time": 1 },
+            { "shape": ["######"], "cost": 1, "time": 1 },
+            { "shape": ["#", "#"], "cost": 2, "time": 1 }
+        ]
+    }"#######;
+    let Err(CatalogueError::Invalid(problems)) = parse_catalogue(json) else {
+        panic!("should be invalid");
+    };
+    assert_eq!(
+        problems,
+        vec![
+            PatchProblem::BadShape {
+                idx: 1,
+                err: ShapeError::Disconnected
+            },
+            PatchProblem::TooBig {
+                idx: 2,
+                width: 6,
+                height: 1
+            },
+            PatchProblem::Duplicate {
+                first: 0,
+                second: 3
+            },
+            PatchProblem::StartPatch { found: 2 },
+        ]
+    );
+
+    assert!(matches!(parse_catalogue("{"), Err(CatalogueError::Json(_))));
+}
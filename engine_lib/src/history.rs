@@ -0,0 +1,99 @@
+use std::mem;
+
+use crate::{
+    board::{BoardGame, GameEvent, MoveError},
+    moves::Move,
+};
+
+// 悔棋用的历史
+// 存行动之前的整个局面，撤销时直接换回去，钱、时间板、特殊布都不用反着算
+// BoardGame 里的拼布和掩码是 Arc 共享的，clone 很便宜
+#[derive(Clone, Debug, Default)]
+pub struct History {
+    // (行动之前的局面, 行动)
+    undo: Vec<(BoardGame, Move)>,
+    // (撤销之前的局面, 行动)
+    redo: Vec<(BoardGame, Move)>,
+}
+
+impl History {
+    // 执行行动并记下来，会清掉能重做的行动
+    pub fn play(&mut self, game: &mut BoardGame, mv: &Move) -> Result<Vec<GameEvent>, MoveError> {
+        let before = game.clone();
+        let events = game.play(mv)?;
+        self.undo.push((before, *mv));
+        self.redo.clear();
+        Ok(events)
+    }
+
+    // 撤销最近一步，返回被撤销的行动
+    pub fn undo(&mut self, game: &mut BoardGame) -> Option<Move> {
+        let (before, mv) = self.undo.pop()?;
+        let after = mem::replace(game, before);
+        self.redo.push((after, mv));
+        Some(mv)
+    }
+
+    // 重做最近撤销的一步
+    pub fn redo(&mut self, game: &mut BoardGame) -> Option<Move> {
+        let (after, mv) = self.redo.pop()?;
+        let before = mem::replace(game, after);
+        self.undo.push((before, mv));
+        Some(mv)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    // 到当前局面为止走过的行动
+    pub fn moves(&self) -> impl Iterator<Item = &Move> {
+        self.undo.iter().map(|(_, mv)| mv)
+    }
+}
+
+#[test]
+fn test_history() {
+    use crate::{new_patches, polyomino::Orientation};
+
+    let mut game = BoardGame::with_patches(new_patches(), 0);
+    let start = game.clone();
+    let mut history = History::default();
+    assert_eq!(history.undo(&mut game), None);
+
+    let put = Move::Put {
+        idx: 0,
+        offset: (0, 0),
+        orient: Orientation::default(),
+    };
+    history.play(&mut game, &put).unwrap();
+    history.play(&mut game, &Move::Advance).unwrap();
+    let end = game.clone();
+
+    // 非法行动不记
+    assert!(history.play(&mut game, &put).is_err());
+    assert_eq!(history.moves().count(), 2);
+
+    assert_eq!(history.undo(&mut game), Some(Move::Advance));
+    assert_eq!(history.undo(&mut game), Some(put));
+    assert!(!history.can_undo());
+    assert_eq!(game.players[0].money, start.players[0].money);
+    assert_eq!(game.players[0].quilt, start.players[0].quilt);
+    assert_eq!(game.patch_pos, start.patch_pos);
+    assert_eq!(game.bank_money, start.bank_money);
+
+    assert_eq!(history.redo(&mut game), Some(put));
+    assert_eq!(history.redo(&mut game), Some(Move::Advance));
+    assert!(!history.can_redo());
+    assert_eq!(game.players[1].pos_idx, end.players[1].pos_idx);
+    assert_eq!(game.players[1].money, end.players[1].money);
+
+    // 撤销之后走了新的一步，就不能再重做
+    history.undo(&mut game);
+    history.play(&mut game, &Move::Advance).unwrap();
+    assert!(!history.can_redo());
+}
@@ -0,0 +1,31 @@
+// Patchwork 规则引擎
+// 不依赖 bevy / egui / web-sys，前端、backend 和离线工具共用同一套规则
+pub mod bitboard;
+pub mod board;
+pub mod bot;
+pub mod catalogue;
+pub mod history;
+pub mod mcts;
+pub mod moves;
+pub mod notation;
+pub mod patches;
+pub mod polyomino;
+pub mod rng;
+pub mod score;
+pub mod snapshot;
+pub mod solver;
+
+pub use bitboard::{PlacementMask, Quilt};
+pub use board::{BoardGame, GameEvent, MoveError, Placement, Player};
+pub use bot::{Bot, GreedyBot, Heuristic};
+pub use catalogue::{CatalogueError, load_catalogue, parse_catalogue};
+pub use history::History;
+pub use mcts::{Difficulty, Mcts, MctsBot, MctsConfig, RolloutPolicy};
+pub use moves::Move;
+pub use notation::{GameRecord, NotationError};
+pub use patches::{Patch, new_patches, setup_patches};
+pub use polyomino::{Orientation, Polyomino, ShapeDirection};
+pub use rng::GameRng;
+pub use score::ScoreBreakdown;
+pub use snapshot::{Snapshot, SnapshotError};
+pub use solver::{RankedPlacement, SolverWeights};
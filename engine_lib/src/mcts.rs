@@ -0,0 +1,453 @@
+use std::cmp::Reverse;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::{Duration, Instant};
+
+use crate::{
+    board::{BoardGame, QUILT_SIZE},
+    bot::{Bot, Heuristic},
+    moves::Move,
+    rng::GameRng,
+};
+
+// 蒙特卡洛树搜索
+// 每块拼布只展开拼布板估值最好的几个摆放，不然一层就有上千个分支
+// 搜索可以分段跑 (Mcts::run)，wasm 上没有线程，前端每帧跑一点
+
+// rollout 时怎么走子，换策略就实现这个
+pub trait RolloutPolicy {
+    // 游戏结束时是 None
+    fn pick(&mut self, game: &BoardGame, rng: &mut GameRng) -> Option<Move>;
+}
+
+// 完全随机: 在能买的拼布和前进里随便挑，随便找个位置放
+#[derive(Clone, Debug, Default)]
+pub struct RandomRollout;
+
+impl RolloutPolicy for RandomRollout {
+    fn pick(&mut self, game: &BoardGame, rng: &mut GameRng) -> Option<Move> {
+        if game.is_game_over() {
+            return None;
+        }
+        let player = game.current_player();
+        if !game.pending_special.is_empty() {
+            let quilt = &game.players[player].quilt;
+            let empty: Vec<(usize, usize)> = (0..QUILT_SIZE)
+                .flat_map(|row| (0..QUILT_SIZE).map(move |col| (col, row)))
+                .filter(|&(col, row)| !quilt.is_set(col, row))
+                .collect();
+            let offset = *empty.get(rng.below(empty.len().max(1)))?;
+            return Some(Move::PutSpecial { offset });
+        }
+
+        let buyable = game.buyable_patches();
+        let pick = rng.below(buyable.len() + 1);
+        let Some(&idx) = buyable.get(pick) else {
+            return Some(Move::Advance);
+        };
+        let placements: Vec<_> = game.placements(idx, player).collect();
+        let p = placements[rng.below(placements.len())];
+        Some(Move::Put {
+            idx,
+            offset: p.offset,
+            orient: p.orient,
+        })
+    }
+}
+
+// 粗略贪心: 按 面积、收入、花费 挑拼布，放在拼布板估值最好的位置
+// 偶尔随机走一步，免得每次 rollout 都一样
+#[derive(Clone, Debug)]
+pub struct HeuristicRollout {
+    pub heuristic: Heuristic,
+    // 随机走一步的概率 (百分比)
+    pub random_percent: usize,
+}
+
+impl Default for HeuristicRollout {
+    fn default() -> Self {
+        Self {
+            heuristic: Heuristic::default(),
+            random_percent: 10,
+        }
+    }
+}
+
+impl RolloutPolicy for HeuristicRollout {
+    fn pick(&mut self, game: &BoardGame, rng: &mut GameRng) -> Option<Move> {
+        if game.is_game_over() {
+            return None;
+        }
+        if rng.below(100) < self.random_percent {
+            return RandomRollout.pick(game, rng);
+        }
+        let h = &self.heuristic;
+        candidates(game, h, 1)
+            .into_iter()
+            .max_by(|a, b| quick_value(game, h, a).total_cmp(&quick_value(game, h, b)))
+    }
+}
+
+// 不 clone 局面的粗略估值，rollout 里用
+fn quick_value(game: &BoardGame, h: &Heuristic, mv: &Move) -> f64 {
+    let player = game.current_player();
+    let p = &game.players[player];
+    match *mv {
+        Move::Put { idx, .. } => {
+            let patch = &game.patches[idx];
+            let (cost, time) = patch.bt;
+            let to = p.pos_idx + time;
+            let incomes_left = game.button_pos.iter().filter(|&&b| b > to).count();
+            h.empty * patch.area() as f64 + h.income * (patch.button * incomes_left) as f64
+                - cost as f64
+                - h.time * time as f64
+        }
+        // 前进拿的纽扣正好抵掉花的时间
+        _ => 0.0,
+    }
+}
+
+// 展开节点时考虑的行动: 每块能买的拼布取拼布板估值最好的 k 个摆放，加上前进
+// 有特殊布要放时取最好的 k 个格子
+pub fn candidates(game: &BoardGame, h: &Heuristic, k: usize) -> Vec<Move> {
+    if game.is_game_over() {
+        return vec![];
+    }
+    let player = game.current_player();
+    let quilt = game.players[player].quilt;
+    let bonus_open = game.bonus_owner.is_none();
+
+    if !game.pending_special.is_empty() {
+        let mut squares: Vec<((usize, usize), f64)> = (0..QUILT_SIZE)
+            .flat_map(|row| (0..QUILT_SIZE).map(move |col| (col, row)))
+            .filter(|&(col, row)| !quilt.is_set(col, row))
+            .map(|(col, row)| {
+                let mut q = quilt;
+                q.set(col, row);
+                ((col, row), h.quilt_value(&q, bonus_open))
+            })
+            .collect();
+        squares.sort_by(|a, b| b.1.total_cmp(&a.1));
+        return squares
+            .into_iter()
+            .take(k)
+            .map(|(offset, _)| Move::PutSpecial { offset })
+            .collect();
+    }
+
+    let mut moves = vec![];
+    for idx in game.buyable_patches() {
+        let mut placements: Vec<_> = game
+            .placements(idx, player)
+            .map(|p| {
+                let mut q = quilt;
+                q.place(p.mask);
+                (p, h.quilt_value(&q, bonus_open))
+            })
+            .collect();
+        placements.sort_by(|a, b| b.1.total_cmp(&a.1));
+        moves.extend(placements.into_iter().take(k).map(|(p, _)| Move::Put {
+            idx,
+            offset: p.offset,
+            orient: p.orient,
+        }));
+    }
+    moves.push(Move::Advance);
+    moves
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct MctsConfig {
+    // 最多跑几次模拟
+    pub iterations: usize,
+    // 最多想多久 (毫秒)，wasm 上没有时钟，只看次数
+    pub time_limit_ms: Option<u64>,
+    // UCT 的探索系数
+    pub exploration: f64,
+    // 每块拼布展开几个摆放
+    pub placements_per_patch: usize,
+    pub seed: u64,
+    pub heuristic: Heuristic,
+}
+
+impl Default for MctsConfig {
+    fn default() -> Self {
+        Difficulty::Normal.config()
+    }
+}
+
+// 难度预设: 模拟次数和思考时间谁先到就停，MctsBot 在 wasm 上没有时钟只看次数
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard];
+
+    pub fn config(self) -> MctsConfig {
+        let (iterations, time_limit_ms, placements_per_patch) = match self {
+            Difficulty::Easy => (100, 500, 1),
+            Difficulty::Normal => (600, 1500, 2),
+            Difficulty::Hard => (2500, 4000, 3),
+        };
+        MctsConfig {
+            iterations,
+            time_limit_ms: Some(time_limit_ms),
+            exploration: 1.4,
+            placements_per_patch,
+            seed: 0,
+            heuristic: Heuristic::default(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Node {
+    // 从父节点走到这里的行动，根节点没有
+    mv: Option<Move>,
+    // 走这一步的玩家，赢了就给他记一分
+    player: usize,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    // 还没展开的行动
+    untried: Vec<Move>,
+    visits: u32,
+    wins: f64,
+}
+
+// 一次搜索，可以分好几段跑完
+#[derive(Clone, Debug)]
+pub struct Mcts {
+    root: BoardGame,
+    nodes: Vec<Node>,
+    rng: GameRng,
+    config: MctsConfig,
+    iterations: usize,
+}
+
+impl Mcts {
+    pub fn new(game: &BoardGame, config: MctsConfig) -> Self {
+        let root = Node {
+            mv: None,
+            player: game.current_player(),
+            parent: None,
+            children: vec![],
+            untried: candidates(game, &config.heuristic, config.placements_per_patch),
+            visits: 0,
+            wins: 0.0,
+        };
+        Self {
+            root: game.clone(),
+            nodes: vec![root],
+            rng: GameRng::new(config.seed),
+            config,
+            iterations: 0,
+        }
+    }
+
+    pub fn iterations(&self) -> usize {
+        self.iterations
+    }
+
+    // 次数用完了
+    pub fn is_done(&self) -> bool {
+        self.iterations >= self.config.iterations
+    }
+
+    // 再跑 n 次模拟，不超过总次数
+    pub fn run(&mut self, rollout: &mut dyn RolloutPolicy, n: usize) {
+        for _ in 0..n {
+            if self.is_done() {
+                break;
+            }
+            self.iterate(rollout);
+        }
+    }
+
+    fn iterate(&mut self, rollout: &mut dyn RolloutPolicy) {
+        let mut game = self.root.clone();
+        let mut node = 0;
+
+        // 选择: 一路按 UCT 往下走，直到有没展开的行动
+        while self.nodes[node].untried.is_empty() && !self.nodes[node].children.is_empty() {
+            node = self.select(node);
+            let mv = self.nodes[node].mv.expect("child has a move");
+            game.play(&mv).expect("tree moves are legal");
+        }
+
+        // 展开一个行动
+        if !self.nodes[node].untried.is_empty() {
+            let i = self.rng.below(self.nodes[node].untried.len());
+            let mv = self.nodes[node].untried.swap_remove(i);
+            let player = game.current_player();
+            game.play(&mv).expect("candidate moves are legal");
+            let k = self.config.placements_per_patch;
+            let child = Node {
+                mv: Some(mv),
+                player,
+                parent: Some(node),
+                children: vec![],
+                untried: candidates(&game, &self.config.heuristic, k),
+                visits: 0,
+                wins: 0.0,
+            };
+            self.nodes.push(child);
+            let child = self.nodes.len() - 1;
+            self.nodes[node].children.push(child);
+            node = child;
+        }
+
+        // 模拟到终局
+        while let Some(mv) = rollout.pick(&game, &mut self.rng) {
+            if game.play(&mv).is_err() {
+                break;
+            }
+        }
+        let winner = game.winner();
+
+        // 回传
+        let mut cur = Some(node);
+        while let Some(i) = cur {
+            let n = &mut self.nodes[i];
+            n.visits += 1;
+            if winner == Some(n.player) {
+                n.wins += 1.0;
+            }
+            cur = n.parent;
+        }
+        self.iterations += 1;
+    }
+
+    fn select(&self, node: usize) -> usize {
+        let ln = (self.nodes[node].visits.max(1) as f64).ln();
+        let uct = |i: usize| {
+            let n = &self.nodes[i];
+            let visits = n.visits.max(1) as f64;
+            n.wins / visits + self.config.exploration * (ln / visits).sqrt()
+        };
+        *self.nodes[node]
+            .children
+            .iter()
+            .max_by(|&&a, &&b| uct(a).total_cmp(&uct(b)))
+            .expect("node has children")
+    }
+
+    // 访问次数最多的行动，还没跑过就用启发式挑一个
+    pub fn best_move(&self) -> Option<Move> {
+        let root = &self.nodes[0];
+        root.children
+            .iter()
+            .max_by_key(|&&i| self.nodes[i].visits)
+            .and_then(|&i| self.nodes[i].mv)
+            .or_else(|| root.untried.first().copied())
+    }
+
+    // 根节点每个行动的 (行动, 访问次数, 胜率)，给提示和调试看
+    pub fn stats(&self) -> Vec<(Move, u32, f64)> {
+        let mut stats: Vec<_> = self.nodes[0]
+            .children
+            .iter()
+            .filter_map(|&i| {
+                let n = &self.nodes[i];
+                Some((n.mv?, n.visits, n.wins / n.visits.max(1) as f64))
+            })
+            .collect();
+        stats.sort_by_key(|s| Reverse(s.1));
+        stats
+    }
+}
+
+// 用 MCTS 下棋的电脑玩家
+pub struct MctsBot {
+    pub config: MctsConfig,
+    pub rollout: Box<dyn RolloutPolicy + Send + Sync>,
+}
+
+impl MctsBot {
+    pub fn new(difficulty: Difficulty) -> Self {
+        Self::with_config(difficulty.config())
+    }
+
+    pub fn with_config(config: MctsConfig) -> Self {
+        Self {
+            config,
+            rollout: Box::new(HeuristicRollout::default()),
+        }
+    }
+}
+
+impl Bot for MctsBot {
+    fn name(&self) -> &str {
+        "mcts"
+    }
+
+    fn choose(&mut self, game: &BoardGame) -> Option<Move> {
+        let mut search = Mcts::new(game, self.config);
+        // 每想一步换个 seed，同样的 seed 和局面只看次数时结果一样
+        self.config.seed = self.config.seed.wrapping_add(1);
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let deadline = self
+            .config
+            .time_limit_ms
+            .map(|ms| Instant::now() + Duration::from_millis(ms));
+        while !search.is_done() {
+            search.run(self.rollout.as_mut(), 16);
+            #[cfg(not(target_arch = "wasm32"))]
+            if deadline.is_some_and(|d| Instant::now() >= d) {
+                break;
+            }
+        }
+        search.best_move()
+    }
+}
+
+#[test]
+fn test_mcts() {
+    use crate::bot::GreedyBot;
+
+    let game = BoardGame::new(4);
+    let mut config = Difficulty::Easy.config();
+    config.iterations = 50;
+
+    // 分段跑和一次跑完结果一样
+    let mut a = Mcts::new(&game, config);
+    a.run(&mut HeuristicRollout::default(), 50);
+    let mut b = Mcts::new(&game, config);
+    for _ in 0..10 {
+        b.run(&mut HeuristicRollout::default(), 5);
+    }
+    assert!(a.is_done() && b.is_done());
+    assert_eq!(a.best_move(), b.best_move());
+    assert_eq!(a.stats().iter().map(|s| s.1).sum::<u32>(), 50);
+    assert!(game.check_move(&a.best_move().unwrap()).is_ok());
+
+    // 特殊布也能搜
+    let mut g = game.clone();
+    g.pending_special.push(0);
+    let mut s = Mcts::new(&g, config);
+    s.run(&mut RandomRollout, 20);
+    assert!(matches!(s.best_move(), Some(Move::PutSpecial { .. })));
+
+    // 和贪心下一整局
+    config.iterations = 10;
+    let mut bots: [Box<dyn Bot>; 2] = [
+        Box::new(MctsBot::with_config(config)),
+        Box::new(GreedyBot::default()),
+    ];
+    let mut g = BoardGame::new(6);
+    while !g.is_game_over() {
+        let mv = bots[g.current_player()].choose(&g).unwrap();
+        g.play(&mv).unwrap();
+    }
+    assert!(g.winner().is_some());
+
+    // 次数没跑完，到时间也会停
+    config.iterations = usize::MAX;
+    config.time_limit_ms = Some(50);
+    let start = Instant::now();
+    assert!(MctsBot::with_config(config).choose(&game).is_some());
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
@@ -0,0 +1,139 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    board::{BoardGame, GameEvent, MoveError, QUILT_SIZE},
+    polyomino::Orientation,
+};
+
+// 玩家在自己回合可以做的行动
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub enum Move {
+    // 买下拼布放到拼布板上 offset: (col, row)
+    Put {
+        idx: usize,
+        offset: (usize, usize),
+        orient: Orientation,
+    },
+    // 前进到对手前一格并拿纽扣
+    Advance,
+    // 放下拿到的 1x1 特殊布 offset: (col, row)
+    PutSpecial { offset: (usize, usize) },
+}
+
+impl BoardGame {
+    pub fn check_move(&self, mv: &Move) -> Result<(), MoveError> {
+        match *mv {
+            Move::Put {
+                idx,
+                offset,
+                orient,
+            } => self.check_put(idx, offset, orient),
+            Move::Advance => {
+                if self.is_game_over() {
+                    Err(MoveError::GameOver)
+                } else if !self.pending_special.is_empty() {
+                    Err(MoveError::SpecialPending)
+                } else {
+                    Ok(())
+                }
+            }
+            Move::PutSpecial { offset } => self.check_put_special(offset),
+        }
+    }
+
+    // 当前玩家所有合法的行动
+    // 买拼布按 市场 x 去重朝向 x 锚点 展开，有特殊布没放时只能放特殊布
+    pub fn legal_moves(&self) -> Vec<Move> {
+        if self.is_game_over() {
+            return vec![];
+        }
+        let player = self.current_player();
+
+        if !self.pending_special.is_empty() {
+            let quilt = &self.players[player].quilt;
+            return (0..QUILT_SIZE)
+                .flat_map(|row| (0..QUILT_SIZE).map(move |col| (col, row)))
+                .filter(|&(col, row)| !quilt.is_set(col, row))
+                .map(|offset| Move::PutSpecial { offset })
+                .collect();
+        }
+
+        let mut moves: Vec<Move> = self
+            .buyable_patches()
+            .into_iter()
+            .flat_map(|idx| {
+                self.placements(idx, player).map(move |p| Move::Put {
+                    idx,
+                    offset: p.offset,
+                    orient: p.orient,
+                })
+            })
+            .collect();
+        moves.push(Move::Advance);
+        moves
+    }
+
+    // 市场里当前玩家买得起、也放得下的拼布
+    pub fn buyable_patches(&self) -> Vec<usize> {
+        if self.is_game_over() || !self.pending_special.is_empty() {
+            return vec![];
+        }
+        let player = self.current_player();
+        self.market_choices()
+            .into_iter()
+            .filter(|&idx| {
+                self.patches[idx].bt.0 <= self.players[player].money
+                    && self.placements(idx, player).next().is_some()
+            })
+            .collect()
+    }
+
+    // 校验并执行一个行动
+    pub fn play(&mut self, mv: &Move) -> Result<Vec<GameEvent>, MoveError> {
+        self.check_move(mv)?;
+        match *mv {
+            Move::Put {
+                idx,
+                offset,
+                orient,
+            } => self.put(idx, offset, orient),
+            Move::Advance => self.advance(),
+            Move::PutSpecial { offset } => self.put_special(offset),
+        }
+    }
+}
+
+#[test]
+fn test_legal_moves() {
+    use crate::{board::TIME_TRACK_END, new_patches};
+
+    let mut game = BoardGame::with_patches(new_patches(), 0);
+    // 开局 0 号 5 个纽扣，(10, 4) 的买不起
+    assert_eq!(game.market_choices(), vec![0, 1, 2]);
+    assert_eq!(game.buyable_patches(), vec![0, 1]);
+
+    let moves = game.legal_moves();
+    let puts = |idx| {
+        moves
+            .iter()
+            .filter(|m| matches!(m, Move::Put { idx: i, .. } if *i == idx))
+            .count()
+    };
+    assert_eq!(puts(0), game.masks[0].distinct.len());
+    assert_eq!(puts(2), 0);
+    assert_eq!(moves.last(), Some(&Move::Advance));
+    assert!(moves.iter().all(|m| game.check_move(m).is_ok()));
+
+    // 拿到特殊布之后只能放特殊布
+    game.players[0].quilt.set(0, 0);
+    game.pending_special.push(0);
+    let moves = game.legal_moves();
+    assert_eq!(moves.len(), 80);
+    assert!(!moves.contains(&Move::PutSpecial { offset: (0, 0) }));
+    assert!(game.buyable_patches().is_empty());
+
+    game.pending_special.clear();
+    game.players[0].pos_idx = TIME_TRACK_END;
+    game.players[1].pos_idx = TIME_TRACK_END;
+    assert!(game.legal_moves().is_empty());
+}
@@ -0,0 +1,295 @@
+use std::fmt;
+
+use crate::{
+    board::{BoardGame, MoveError, QUILT_SIZE},
+    moves::Move,
+    polyomino::{Orientation, ShapeDirection},
+};
+
+// 行动的文字记法
+//   买拼布: <拼布目录编号><朝向 E/S/W/N>[f 翻面]@<格子>  例如 12Sf@c4
+//   前进:   A
+//   特殊布: *<格子>  例如 *e5
+// 格子: 列 a-i，行 1-9，a1 是 (0, 0)
+// 用拼布目录编号而不是圈里的位置，换了 seed 也能看懂是哪块
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum NotationError {
+    // 写法不对
+    Syntax(String),
+    // 这局里没有这个编号的拼布
+    UnknownPatch(usize),
+    // 头信息不对
+    Header(String),
+    // 第 ply 步 (从 0 开始) 在当时的局面下不合法
+    Illegal { ply: usize, mv: String, err: MoveError },
+}
+
+impl fmt::Display for NotationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NotationError::Syntax(s) => write!(f, "bad move notation: {:?}", s),
+            NotationError::UnknownPatch(id) => write!(f, "no patch with id {}", id),
+            NotationError::Header(s) => write!(f, "bad record header: {}", s),
+            NotationError::Illegal { ply, mv, err } => {
+                write!(f, "move {} ({}) is illegal: {:?}", ply + 1, mv, err)
+            }
+        }
+    }
+}
+
+impl std::error::Error for NotationError {}
+
+pub fn square_name((col, row): (usize, usize)) -> String {
+    format!("{}{}", (b'a' + col as u8) as char, row + 1)
+}
+
+pub fn parse_square(s: &str) -> Option<(usize, usize)> {
+    let mut chars = s.chars();
+    let col = chars.next()?;
+    let row: usize = chars.as_str().parse().ok()?;
+    if !('a'..='i').contains(&col) || !(1..=QUILT_SIZE).contains(&row) {
+        return None;
+    }
+    Some((col as usize - 'a' as usize, row - 1))
+}
+
+fn dir_char(dir: ShapeDirection) -> char {
+    match dir {
+        ShapeDirection::East => 'E',
+        ShapeDirection::South => 'S',
+        ShapeDirection::West => 'W',
+        ShapeDirection::North => 'N',
+    }
+}
+
+fn parse_dir(c: char) -> Option<ShapeDirection> {
+    ShapeDirection::ALL.into_iter().find(|&d| dir_char(d) == c)
+}
+
+impl BoardGame {
+    pub fn move_notation(&self, mv: &Move) -> String {
+        match *mv {
+            Move::Put {
+                idx,
+                offset,
+                orient,
+            } => format!(
+                "{}{}{}@{}",
+                self.patches[idx].id,
+                dir_char(orient.dir),
+                if orient.flip { "f" } else { "" },
+                square_name(offset)
+            ),
+            Move::Advance => "A".to_string(),
+            Move::PutSpecial { offset } => format!("*{}", square_name(offset)),
+        }
+    }
+
+    // 只解析，不检查合不合法
+    pub fn parse_move(&self, s: &str) -> Result<Move, NotationError> {
+        let syntax = || NotationError::Syntax(s.to_string());
+        if s == "A" {
+            return Ok(Move::Advance);
+        }
+        if let Some(square) = s.strip_prefix('*') {
+            let offset = parse_square(square).ok_or_else(syntax)?;
+            return Ok(Move::PutSpecial { offset });
+        }
+
+        let (head, square) = s.split_once('@').ok_or_else(syntax)?;
+        let offset = parse_square(square).ok_or_else(syntax)?;
+        let (head, flip) = match head.strip_suffix('f') {
+            Some(head) => (head, true),
+            None => (head, false),
+        };
+        let dir = head.chars().last().and_then(parse_dir).ok_or_else(syntax)?;
+        let id: usize = head[..head.len() - 1].parse().map_err(|_| syntax())?;
+        let idx = self
+            .patches
+            .iter()
+            .position(|p| p.id == id)
+            .ok_or(NotationError::UnknownPatch(id))?;
+        Ok(Move::Put {
+            idx,
+            offset,
+            orient: Orientation { dir, flip },
+        })
+    }
+}
+
+// 一局的棋谱
+//   [Player0 "..."]
+//   [Player1 "..."]
+//   [Seed "42"]
+//   [Date "2026-10-18"]
+//   [Result "..."]
+//
+//   12Sf@c4 A *e5 ...
+// 同一份拼布目录 + seed 就能复现开局，所以只记行动
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct GameRecord {
+    pub players: [String; 2],
+    pub seed: u64,
+    pub date: String,
+    // 没下完是空的
+    pub result: String,
+    pub moves: Vec<String>,
+}
+
+impl GameRecord {
+    // start 是开局的局面，moves 是从开局起走过的行动
+    pub fn from_moves<'a>(start: &BoardGame, moves: impl IntoIterator<Item = &'a Move>) -> Self {
+        Self {
+            players: ["player 0".to_string(), "player 1".to_string()],
+            seed: start.seed,
+            moves: moves.into_iter().map(|mv| start.move_notation(mv)).collect(),
+            ..Default::default()
+        }
+    }
+
+    // 终局写上比分和赢家
+    pub fn set_result(&mut self, game: &BoardGame) {
+        self.result = match game.winner() {
+            Some(w) => format!(
+                "{}:{} winner {}",
+                game.score(0).total(),
+                game.score(1).total(),
+                self.players[w]
+            ),
+            None => String::new(),
+        };
+    }
+
+    pub fn to_text(&self) -> String {
+        // 值里不能有双引号
+        let value = |s: &str| s.replace('"', "'");
+        let mut text = String::new();
+        for (key, v) in [
+            ("Player0", value(&self.players[0])),
+            ("Player1", value(&self.players[1])),
+            ("Seed", self.seed.to_string()),
+            ("Date", value(&self.date)),
+            ("Result", value(&self.result)),
+        ] {
+            text += &format!("[{} \"{}\"]\n", key, v);
+        }
+        text += "\n";
+        text += &self.moves.join(" ");
+        text += "\n";
+        text
+    }
+
+    pub fn parse(text: &str) -> Result<Self, NotationError> {
+        let mut record = GameRecord::default();
+        let mut seed = None;
+        for line in text.lines().map(str::trim) {
+            let Some(header) = line.strip_prefix('[') else {
+                record.moves.extend(line.split_whitespace().map(str::to_string));
+                continue;
+            };
+            let bad = || NotationError::Header(line.to_string());
+            let (key, v) = header
+                .strip_suffix("\"]")
+                .and_then(|h| h.split_once(" \""))
+                .ok_or_else(bad)?;
+            let v = v.to_string();
+            match key {
+                "Player0" => record.players[0] = v,
+                "Player1" => record.players[1] = v,
+                "Seed" => seed = Some(v.parse().map_err(|_| bad())?),
+                "Date" => record.date = v,
+                "Result" => record.result = v,
+                // 不认识的头信息跳过
+                _ => {}
+            }
+        }
+        record.seed = seed.ok_or(NotationError::Header("missing Seed".to_string()))?;
+        Ok(record)
+    }
+
+    // 从开局一步步走，返回每一步之后的局面，第 0 个是开局
+    pub fn replay(&self, start: BoardGame) -> Result<Vec<BoardGame>, NotationError> {
+        if start.seed != self.seed {
+            return Err(NotationError::Header(format!(
+                "seed {} does not match start {}",
+                self.seed, start.seed
+            )));
+        }
+        let mut states = vec![start];
+        for (ply, s) in self.moves.iter().enumerate() {
+            let mut game = states[ply].clone();
+            let mv = game.parse_move(s)?;
+            game.play(&mv).map_err(|err| NotationError::Illegal {
+                ply,
+                mv: s.clone(),
+                err,
+            })?;
+            states.push(game);
+        }
+        Ok(states)
+    }
+}
+
+#[test]
+fn test_move_notation() {
+    let game = BoardGame::new(3);
+    assert_eq!(square_name((2, 3)), "c4");
+    assert_eq!(parse_square("i9"), Some((8, 8)));
+    assert_eq!(parse_square("j1"), None);
+    assert_eq!(parse_square("a10"), None);
+
+    let id = game.patches[5].id;
+    let mv = Move::Put {
+        idx: 5,
+        offset: (2, 3),
+        orient: Orientation {
+            dir: ShapeDirection::South,
+            flip: true,
+        },
+    };
+    assert_eq!(game.move_notation(&mv), format!("{}Sf@c4", id));
+    for mv in [mv, Move::Advance, Move::PutSpecial { offset: (4, 4) }] {
+        assert_eq!(game.parse_move(&game.move_notation(&mv)), Ok(mv));
+    }
+    assert_eq!(game.parse_move("*e5"), Ok(Move::PutSpecial { offset: (4, 4) }));
+
+    assert_eq!(game.parse_move("99E@a1"), Err(NotationError::UnknownPatch(99)));
+    for bad in ["", "B", "*z1", "3@a1", "3X@a1", "E@a1", "3E"] {
+        assert!(matches!(game.parse_move(bad), Err(NotationError::Syntax(_))), "{}", bad);
+    }
+}
+
+#[test]
+fn test_game_record() {
+    let start = BoardGame::new(11);
+    let mut game = start.clone();
+    let mut moves = vec![];
+    while !game.is_game_over() {
+        let mv = game.legal_moves()[0];
+        game.play(&mv).unwrap();
+        moves.push(mv);
+    }
+
+    let mut record = GameRecord::from_moves(&start, &moves);
+    record.date = "2026-10-18".to_string();
+    record.set_result(&game);
+    assert!(!record.result.is_empty());
+
+    let parsed = GameRecord::parse(&record.to_text()).unwrap();
+    assert_eq!(parsed, record);
+
+    let states = parsed.replay(start.clone()).unwrap();
+    assert_eq!(states.len(), moves.len() + 1);
+    assert_eq!(states.last().unwrap().snapshot(), game.snapshot());
+
+    // 非法的一步报出是第几步
+    let mut bad = record.clone();
+    bad.moves.insert(0, "*a1".to_string());
+    assert!(matches!(
+        bad.replay(start.clone()),
+        Err(NotationError::Illegal { ply: 0, .. })
+    ));
+    assert!(record.replay(BoardGame::new(12)).is_err());
+    assert!(GameRecord::parse("A A").is_err());
+}
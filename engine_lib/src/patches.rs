@@ -0,0 +1,155 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    catalogue::{DEFAULT_CATALOGUE, parse_catalogue},
+    polyomino::{Orientation, Polyomino},
+    rng::GameRng,
+};
+
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub struct Patch {
+    // 在拼布目录里的编号，洗牌之后也不变
+    pub id: usize,
+    // 形状
+    pub shape: Polyomino,
+    // (纽扣花费, 时间花费)
+    pub bt: (usize, usize),
+    // 纽扣收入
+    pub button: usize,
+}
+impl Patch {
+    // 以 offset 为原点，按朝向（先翻面再旋转）摆放后占据的格子
+    pub fn get_pos(
+        &self,
+        offset: (isize, isize),
+        orient: impl Into<Orientation>,
+    ) -> Vec<(isize, isize)> {
+        let orient = orient.into();
+        self.shape
+            .cells()
+            .map(|(col, row)| {
+                let (x, y) = orient.apply(col as isize, row as isize);
+                (x + offset.0, y + offset.1)
+            })
+            .collect()
+    }
+
+    // 占几个格子
+    pub fn area(&self) -> usize {
+        self.shape.area()
+    }
+
+    pub fn new(shape: Polyomino) -> Self {
+        Self {
+            id: 0,
+            shape,
+            bt: (0, 0),
+            button: 0,
+        }
+    }
+}
+
+#[test]
+fn test_get_pos() {
+    use crate::polyomino::ShapeDirection;
+    let shape = |rows: &[&str]| Polyomino::from_rows(rows).unwrap();
+
+    let patch = Patch::new(shape(&["#"]));
+    assert_eq!(patch.get_pos((0, 0), ShapeDirection::East), vec![(0, 0)]);
+
+    let patch = Patch::new(shape(&["##"]));
+    assert_eq!(
+        patch.get_pos((0, 0), ShapeDirection::East),
+        vec![(0, 0), (1, 0)]
+    );
+
+    let patch = Patch::new(shape(&["###"]));
+    assert_eq!(
+        patch.get_pos((0, 0), ShapeDirection::East),
+        vec![(0, 0), (1, 0), (2, 0)]
+    );
+
+    let patch = Patch::new(shape(&["###", "#"]));
+    assert_eq!(
+        patch.get_pos((0, 0), ShapeDirection::East),
+        vec![(0, 0), (1, 0), (2, 0), (0, 1)]
+    );
+
+    let patch = Patch::new(shape(&["###", "#"]));
+    assert_eq!(
+        patch.get_pos((0, 0), ShapeDirection::South),
+        vec![(0, 0), (0, -1), (0, -2), (1, 0)]
+    );
+
+    let patch = Patch::new(shape(&["###", "#"]));
+    assert_eq!(
+        patch.get_pos((0, 0), ShapeDirection::West),
+        vec![(0, 0), (-1, 0), (-2, 0), (0, -1)]
+    );
+
+    let patch = Patch::new(shape(&["###", "#"]));
+    assert_eq!(
+        patch.get_pos((2, 2), ShapeDirection::West),
+        vec![(2, 2), (1, 2), (0, 2), (2, 1)]
+    );
+
+    let patch = Patch::new(shape(&["###", "#"]));
+    assert_eq!(
+        patch.get_pos((0, 0), ShapeDirection::North),
+        vec![(0, 0), (0, 1), (0, 2), (-1, 0)]
+    );
+
+    // 翻面之后的四个方向
+    let flip = |dir| Orientation { dir, flip: true };
+    let patch = Patch::new(shape(&["###", "#"]));
+    assert_eq!(
+        patch.get_pos((0, 0), flip(ShapeDirection::East)),
+        vec![(0, 0), (-1, 0), (-2, 0), (0, 1)]
+    );
+    assert_eq!(
+        patch.get_pos((0, 0), flip(ShapeDirection::South)),
+        vec![(0, 0), (0, 1), (0, 2), (1, 0)]
+    );
+    assert_eq!(
+        patch.get_pos((0, 0), flip(ShapeDirection::West)),
+        vec![(0, 0), (1, 0), (2, 0), (0, -1)]
+    );
+    assert_eq!(
+        patch.get_pos((2, 2), flip(ShapeDirection::West)),
+        vec![(2, 2), (3, 2), (4, 2), (2, 1)]
+    );
+    assert_eq!(
+        patch.get_pos((0, 0), flip(ShapeDirection::North)),
+        vec![(0, 0), (0, -1), (0, -2), (-1, 0)]
+    );
+
+    // L 形的 8 个朝向互不相同
+    let mut all: Vec<_> = Orientation::all()
+        .map(|o| {
+            let mut v = patch.get_pos((0, 0), o);
+            v.sort();
+            v
+        })
+        .collect();
+    assert_eq!(all.len(), 8);
+    all.sort();
+    all.dedup();
+    assert_eq!(all.len(), 8);
+
+}
+
+// 内置拼布目录里的 33 块拼布
+pub fn new_patches() -> Vec<Patch> {
+    parse_catalogue(DEFAULT_CATALOGUE).unwrap_or_else(|e| panic!("{}", e))
+}
+
+// 开局摆拼布: 按 seed 洗牌，2x1 的小拼布放在最后
+// 中立指示物在 0 号位，也就是 2x1 顺时针的下一块
+pub fn setup_patches(mut patches: Vec<Patch>, rng: &mut GameRng) -> Vec<Patch> {
+    rng.shuffle(&mut patches);
+    if let Some(i) = patches.iter().position(|p| p.area() == 2) {
+        let n = patches.len();
+        patches.rotate_left((i + 1) % n);
+    }
+    patches
+}
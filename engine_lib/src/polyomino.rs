@@ -0,0 +1,310 @@
+use std::{collections::VecDeque, fmt};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub enum ShapeDirection {
+    East,
+    South,
+    West,
+    North,
+}
+
+impl ShapeDirection {
+    pub const ALL: [ShapeDirection; 4] = [
+        ShapeDirection::East,
+        ShapeDirection::South,
+        ShapeDirection::West,
+        ShapeDirection::North,
+    ];
+
+    // 按 East -> South -> West -> North 的顺序转到下一个
+    pub fn next(self) -> Self {
+        match self {
+            ShapeDirection::East => ShapeDirection::South,
+            ShapeDirection::South => ShapeDirection::West,
+            ShapeDirection::West => ShapeDirection::North,
+            ShapeDirection::North => ShapeDirection::East,
+        }
+    }
+
+    pub fn prev(self) -> Self {
+        self.next().next().next()
+    }
+}
+
+// 拼布的朝向: 旋转 + 是否翻面，一共 8 种
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub struct Orientation {
+    pub dir: ShapeDirection,
+    // 左右翻面，先翻面再旋转
+    pub flip: bool,
+}
+
+impl Default for Orientation {
+    fn default() -> Self {
+        ShapeDirection::East.into()
+    }
+}
+
+impl From<ShapeDirection> for Orientation {
+    fn from(dir: ShapeDirection) -> Self {
+        Self { dir, flip: false }
+    }
+}
+
+impl Orientation {
+    pub fn all() -> impl Iterator<Item = Orientation> {
+        [false, true].into_iter().flat_map(|flip| {
+            ShapeDirection::ALL
+                .into_iter()
+                .map(move |dir| Orientation { dir, flip })
+        })
+    }
+
+    // 0..8，查表用
+    pub fn index(self) -> usize {
+        let dir = match self.dir {
+            ShapeDirection::East => 0,
+            ShapeDirection::South => 1,
+            ShapeDirection::West => 2,
+            ShapeDirection::North => 3,
+        };
+        dir + if self.flip { 4 } else { 0 }
+    }
+
+    pub fn next(self) -> Self {
+        Self {
+            dir: self.dir.next(),
+            ..self
+        }
+    }
+
+    pub fn prev(self) -> Self {
+        Self {
+            dir: self.dir.prev(),
+            ..self
+        }
+    }
+
+    pub fn flipped(self) -> Self {
+        Self {
+            flip: !self.flip,
+            ..self
+        }
+    }
+
+    // 形状里的 (col, row) 变换到朝向之后的位置
+    pub fn apply(self, col: isize, row: isize) -> (isize, isize) {
+        let col = if self.flip { -col } else { col };
+        match self.dir {
+            ShapeDirection::East => (col, row),
+            ShapeDirection::South => (row, -col),
+            ShapeDirection::West => (-col, -row),
+            ShapeDirection::North => (-row, col),
+        }
+    }
+}
+
+// 形状不合法的原因
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ShapeError {
+    // 一个格子都没有
+    Empty,
+    // 不是 '#' 或 '.'
+    BadChar(char),
+    // 格子没有连成一块
+    Disconnected,
+}
+
+impl fmt::Display for ShapeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShapeError::Empty => write!(f, "empty shape"),
+            ShapeError::BadChar(c) => write!(f, "bad char in shape: {:?}", c),
+            ShapeError::Disconnected => write!(f, "shape is not connected"),
+        }
+    }
+}
+
+// 连通的多格骨牌，格子平移到左上角 (0, 0) 开始
+// cells 按 (row, col) 排序
+// 序列化成和拼布目录一样的按行写法
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, Debug)]
+#[serde(into = "Vec<String>", try_from = "Vec<String>")]
+pub struct Polyomino {
+    cells: Vec<(usize, usize)>,
+    width: usize,
+    height: usize,
+}
+
+impl Polyomino {
+    // 按行描述形状: '#' 有格子 '.' 空，行尾的 '.' 可以省略
+    pub fn from_rows(rows: &[&str]) -> Result<Self, ShapeError> {
+        let mut cells = vec![];
+        for (row, line) in rows.iter().enumerate() {
+            for (col, c) in line.chars().enumerate() {
+                match c {
+                    '#' => cells.push((col as isize, row as isize)),
+                    '.' => {}
+                    c => return Err(ShapeError::BadChar(c)),
+                }
+            }
+        }
+        Self::from_cells(cells)
+    }
+
+    // 任意 (col, row) 格子，平移到左上角并校验连通
+    pub fn from_cells(cells: Vec<(isize, isize)>) -> Result<Self, ShapeError> {
+        let min_col = cells.iter().map(|c| c.0).min().ok_or(ShapeError::Empty)?;
+        let min_row = cells.iter().map(|c| c.1).min().ok_or(ShapeError::Empty)?;
+        let mut cells: Vec<(usize, usize)> = cells
+            .iter()
+            .map(|&(c, r)| ((c - min_col) as usize, (r - min_row) as usize))
+            .collect();
+        cells.sort_by_key(|&(c, r)| (r, c));
+        cells.dedup();
+
+        let width = cells.iter().map(|c| c.0).max().unwrap_or(0) + 1;
+        let height = cells.iter().map(|c| c.1).max().unwrap_or(0) + 1;
+        let p = Self {
+            cells,
+            width,
+            height,
+        };
+        if !p.is_connected() {
+            return Err(ShapeError::Disconnected);
+        }
+        Ok(p)
+    }
+
+    // from_rows 的反过来
+    pub fn to_rows(&self) -> Vec<String> {
+        (0..self.height)
+            .map(|row| {
+                (0..self.width)
+                    .map(|col| if self.contains(col, row) { '#' } else { '.' })
+                    .collect()
+            })
+            .collect()
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn area(&self) -> usize {
+        self.cells.len()
+    }
+
+    // (col, row)，按行从上到下、从左到右
+    pub fn cells(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.cells.iter().copied()
+    }
+
+    pub fn contains(&self, col: usize, row: usize) -> bool {
+        self.cells.contains(&(col, row))
+    }
+
+    // 旋转/翻面之后重新平移到左上角
+    pub fn transformed(&self, orient: Orientation) -> Self {
+        let cells = self
+            .cells()
+            .map(|(c, r)| orient.apply(c as isize, r as isize))
+            .collect();
+        Self::from_cells(cells).expect("transform keeps shape connected")
+    }
+
+    // 8 个朝向里最小的那个，用来判断是不是同一个形状
+    pub fn canonical(&self) -> Self {
+        Orientation::all()
+            .map(|o| self.transformed(o))
+            .min()
+            .expect("8 orientations")
+    }
+
+    // 旋转/翻面之后能重合
+    pub fn same_shape(&self, other: &Self) -> bool {
+        self.area() == other.area() && self.canonical() == other.canonical()
+    }
+
+    // 去掉对称重复之后的朝向
+    pub fn distinct_orientations(&self) -> Vec<Orientation> {
+        let mut seen = vec![];
+        Orientation::all()
+            .filter(|&o| {
+                let t = self.transformed(o);
+                if seen.contains(&t) {
+                    false
+                } else {
+                    seen.push(t);
+                    true
+                }
+            })
+            .collect()
+    }
+
+    fn is_connected(&self) -> bool {
+        let Some(&start) = self.cells.first() else {
+            return false;
+        };
+        let mut seen = vec![start];
+        let mut queue = VecDeque::from([start]);
+        while let Some((c, r)) = queue.pop_front() {
+            for n in [
+                (c.wrapping_sub(1), r),
+                (c + 1, r),
+                (c, r.wrapping_sub(1)),
+                (c, r + 1),
+            ] {
+                if self.cells.contains(&n) && !seen.contains(&n) {
+                    seen.push(n);
+                    queue.push_back(n);
+                }
+            }
+        }
+        seen.len() == self.cells.len()
+    }
+}
+
+impl From<Polyomino> for Vec<String> {
+    fn from(p: Polyomino) -> Self {
+        p.to_rows()
+    }
+}
+
+impl TryFrom<Vec<String>> for Polyomino {
+    type Error = ShapeError;
+    fn try_from(rows: Vec<String>) -> Result<Self, Self::Error> {
+        let rows: Vec<&str> = rows.iter().map(String::as_str).collect();
+        Self::from_rows(&rows)
+    }
+}
+
+#[test]
+fn test_polyomino() {
+    let l = Polyomino::from_rows(&["###", "#"]).unwrap();
+    assert_eq!((l.width(), l.height(), l.area()), (3, 2, 4));
+    assert_eq!(l.cells().collect::<Vec<_>>(), vec![(0, 0), (1, 0), (2, 0), (0, 1)]);
+
+    // 竖着的 L 也是同一个形状
+    let l2 = Polyomino::from_rows(&["#", "#", "##"]).unwrap();
+    assert!(l.same_shape(&l2));
+    assert!(!l.same_shape(&Polyomino::from_rows(&["####"]).unwrap()));
+    assert_eq!(l.distinct_orientations().len(), 8);
+    assert_eq!(Polyomino::from_rows(&["##", "##"]).unwrap().distinct_orientations().len(), 1);
+    assert_eq!(Polyomino::from_rows(&["###"]).unwrap().distinct_orientations().len(), 2);
+
+    let t = l.transformed(ShapeDirection::South.into());
+    assert_eq!((t.width(), t.height()), (2, 3));
+    assert_eq!(l.to_rows(), vec!["###", "#.."]);
+    assert_eq!(Polyomino::try_from(l2.to_rows()), Ok(l2));
+
+    assert_eq!(Polyomino::from_rows(&[""]), Err(ShapeError::Empty));
+    assert_eq!(Polyomino::from_rows(&["#.#"]), Err(ShapeError::Disconnected));
+    assert_eq!(Polyomino::from_rows(&["#x"]), Err(ShapeError::BadChar('x')));
+}
@@ -0,0 +1,45 @@
+// 可复现的随机数 (SplitMix64)
+// 不依赖 rand，同一个 seed 在任何平台上都得到同样的局面
+#[derive(Clone, Debug)]
+pub struct GameRng {
+    state: u64,
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    // [0, n)
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    // Fisher-Yates 洗牌
+    pub fn shuffle<T>(&mut self, v: &mut [T]) {
+        for i in (1..v.len()).rev() {
+            let j = self.below(i + 1);
+            v.swap(i, j);
+        }
+    }
+}
+
+#[test]
+fn test_rng_reproducible() {
+    let mut a = GameRng::new(42);
+    let mut b = GameRng::new(42);
+    let mut va: Vec<usize> = (0..33).collect();
+    let mut vb = va.clone();
+    a.shuffle(&mut va);
+    b.shuffle(&mut vb);
+    assert_eq!(va, vb);
+    assert_ne!(va, (0..33).collect::<Vec<_>>());
+}
@@ -0,0 +1,84 @@
+use crate::board::{BoardGame, GameEvent};
+
+// 7x7 板块的分数
+pub const BONUS_TILE_SCORE: isize = 7;
+// 7x7 板块的边长
+pub const BONUS_SIZE: usize = 7;
+
+// 终局计分的明细
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ScoreBreakdown {
+    // 手里的纽扣
+    pub buttons: usize,
+    // 拼布板上的空格，每个扣 2 分
+    pub empty: usize,
+    // 7x7 板块
+    pub bonus: bool,
+}
+
+impl ScoreBreakdown {
+    pub fn total(&self) -> isize {
+        let bonus = if self.bonus { BONUS_TILE_SCORE } else { 0 };
+        self.buttons as isize - 2 * self.empty as isize + bonus
+    }
+}
+
+impl BoardGame {
+    // 放置之后检查 7x7 板块，第一个填满的玩家拿走
+    pub(crate) fn check_bonus(&mut self, player: usize) -> Option<GameEvent> {
+        if self.bonus_owner.is_some() || !self.players[player].quilt.has_full_square(BONUS_SIZE) {
+            return None;
+        }
+        self.bonus_owner = Some(player);
+        Some(GameEvent::BonusTile { player })
+    }
+
+    pub fn score(&self, player: usize) -> ScoreBreakdown {
+        ScoreBreakdown {
+            buttons: self.players[player].money,
+            empty: self.players[player].quilt.empty(),
+            bonus: self.bonus_owner == Some(player),
+        }
+    }
+
+    // 分高的赢，同分先到终点的赢
+    pub fn winner(&self) -> Option<usize> {
+        if !self.is_game_over() {
+            return None;
+        }
+        let (s0, s1) = (self.score(0).total(), self.score(1).total());
+        if s0 != s1 {
+            return Some(if s0 > s1 { 0 } else { 1 });
+        }
+        let [p0, p1] = &self.players;
+        Some(if p0.last_move_tick < p1.last_move_tick { 0 } else { 1 })
+    }
+}
+
+#[test]
+fn test_score() {
+    let mut game = BoardGame::with_patches(crate::new_patches(), 0);
+    assert_eq!(game.score(0).total(), 5 - 2 * 81);
+
+    // 填满左下角 7x7，拿到 7x7 板块
+    for x in 0..BONUS_SIZE {
+        for y in 0..BONUS_SIZE {
+            game.players[1].quilt.set(x, y);
+        }
+    }
+    assert!(!game.players[1].quilt.has_full_square(8));
+    assert_eq!(game.check_bonus(0), None);
+    assert_eq!(game.check_bonus(1), Some(GameEvent::BonusTile { player: 1 }));
+    assert_eq!(game.check_bonus(1), None);
+
+    let s = game.score(1);
+    assert_eq!(
+        s,
+        ScoreBreakdown {
+            buttons: 5,
+            empty: 81 - 49,
+            bonus: true,
+        }
+    );
+    assert_eq!(s.total(), 5 - 64 + 7);
+}
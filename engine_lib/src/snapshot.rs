@@ -0,0 +1,286 @@
+use std::{fmt, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bitboard::{PatchMasks, Quilt},
+    board::{
+        BoardGame, BoardType, Placement, Player, QUILT_SIZE, SPECIAL_PATCH_POS, TIME_TRACK_END,
+        TimeBoardType,
+    },
+    patches::Patch,
+};
+
+// 存档格式的版本，字段有不兼容的改动就加一
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+// 完整局面的存档，存盘、联机、贴到 bug 报告里都用这个
+// 掩码不存，读档的时候按拼布重新算
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub struct Snapshot {
+    pub version: u32,
+    pub seed: u64,
+    pub board_type: BoardType,
+    pub time_board_type: TimeBoardType,
+    pub bank_money: usize,
+    pub players: [Player; 2],
+    pub global_move_tick: usize,
+    pub special_patches: Vec<usize>,
+    pub pending_special: Vec<usize>,
+    pub special_pos: Vec<(usize, (usize, usize))>,
+    pub button_pos: [usize; 9],
+    // 洗过之后围成一圈的顺序
+    pub patches: Vec<Patch>,
+    pub neutral: usize,
+    pub patch_pos: Vec<Option<Placement>>,
+    pub bonus_owner: Option<usize>,
+}
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Json(serde_json::Error),
+    Version { expected: u32, found: u32 },
+    // 字段之间对不上
+    Invalid(String),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::Json(e) => write!(f, "parse snapshot fail: {}", e),
+            SnapshotError::Version { expected, found } => {
+                write!(f, "snapshot version {} not supported, expected {}", found, expected)
+            }
+            SnapshotError::Invalid(s) => write!(f, "invalid snapshot: {}", s),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+impl Snapshot {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("snapshot always serializes")
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, SnapshotError> {
+        let snap: Snapshot = serde_json::from_str(json).map_err(SnapshotError::Json)?;
+        if snap.version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::Version {
+                expected: SNAPSHOT_VERSION,
+                found: snap.version,
+            });
+        }
+        Ok(snap)
+    }
+}
+
+impl BoardGame {
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            version: SNAPSHOT_VERSION,
+            seed: self.seed,
+            board_type: self.board_type,
+            time_board_type: self.time_board_type,
+            bank_money: self.bank_money,
+            players: self.players.clone(),
+            global_move_tick: self.global_move_tick,
+            special_patches: self.special_patches.clone(),
+            pending_special: self.pending_special.clone(),
+            special_pos: self.special_pos.clone(),
+            button_pos: self.button_pos,
+            patches: self.patches.as_ref().clone(),
+            neutral: self.neutral,
+            patch_pos: self.patch_pos.clone(),
+            bonus_owner: self.bonus_owner,
+        }
+    }
+
+    // 从存档恢复，先检查下标都在范围里、各字段对得上，免得之后走子 panic
+    pub fn from_snapshot(snap: Snapshot) -> Result<Self, SnapshotError> {
+        let n = snap.patches.len();
+        let invalid = |s: String| Err(SnapshotError::Invalid(s));
+        if snap.patch_pos.len() != n {
+            return invalid(format!("{} patches but {} positions", n, snap.patch_pos.len()));
+        }
+        if snap.neutral >= n.max(1) {
+            return invalid(format!("neutral {} out of range", snap.neutral));
+        }
+        let players = snap.players.len();
+        let bad_player = snap
+            .patch_pos
+            .iter()
+            .flatten()
+            .map(|p| p.player)
+            .chain(snap.pending_special.iter().copied())
+            .chain(snap.special_pos.iter().map(|p| p.0))
+            .chain(snap.bonus_owner)
+            .find(|&p| p >= players);
+        if let Some(p) = bad_player {
+            return invalid(format!("player {} out of range", p));
+        }
+        if let Some(p) = snap.players.iter().find(|p| p.pos_idx > TIME_TRACK_END) {
+            return invalid(format!("time track position {} out of range", p.pos_idx));
+        }
+
+        // 拼布板要和放下的拼布、特殊布一格不差
+        let masks: Vec<PatchMasks> = snap.patches.iter().map(PatchMasks::new).collect();
+        let mut quilts = [Quilt::default(); 2];
+        for (idx, p) in snap.patch_pos.iter().enumerate() {
+            let Some(p) = p else {
+                continue;
+            };
+            match masks[idx].get(p.offset, p.orient) {
+                Some(mask) if quilts[p.player].fits(mask) => quilts[p.player].place(mask),
+                Some(_) => return invalid(format!("patch {} overlaps", idx)),
+                None => return invalid(format!("patch {} placed outside the quilt", idx)),
+            }
+        }
+        for &(player, (col, row)) in snap.special_pos.iter() {
+            if col >= QUILT_SIZE || row >= QUILT_SIZE {
+                return invalid(format!("special patch ({}, {}) outside the quilt", col, row));
+            }
+            if quilts[player].is_set(col, row) {
+                return invalid(format!("special patch ({}, {}) overlaps", col, row));
+            }
+            quilts[player].set(col, row);
+        }
+        if let Some(p) = (0..players).find(|&p| quilts[p] != snap.players[p].quilt) {
+            return invalid(format!("quilt of player {} does not match its patches", p));
+        }
+
+        // 谁走过的特殊布都已经被拿走，没走到的都还在
+        let furthest = snap.players.iter().map(|p| p.pos_idx).max().unwrap_or(0);
+        let left: Vec<usize> = SPECIAL_PATCH_POS
+            .into_iter()
+            .filter(|&pos| pos > furthest)
+            .collect();
+        if snap.special_patches != left {
+            return invalid(format!(
+                "special patches {:?} left on the time track",
+                snap.special_patches
+            ));
+        }
+        // 拿走的要么放下了、要么还没放、要么拼布板满了作废
+        let taken = SPECIAL_PATCH_POS.len() - left.len();
+        if snap.pending_special.len() + snap.special_pos.len() > taken {
+            return invalid(format!("{} special patches taken", taken));
+        }
+        for p in 0..players {
+            let pending = snap.pending_special.iter().filter(|&&s| s == p).count();
+            if pending > snap.players[p].quilt.empty() {
+                return invalid(format!("player {} has no room for pending special patches", p));
+            }
+        }
+
+        Ok(Self {
+            board_type: snap.board_type,
+            time_board_type: snap.time_board_type,
+            bank_money: snap.bank_money,
+            players: snap.players,
+            global_move_tick: snap.global_move_tick,
+            special_patches: snap.special_patches,
+            pending_special: snap.pending_special,
+            special_pos: snap.special_pos,
+            button_pos: snap.button_pos,
+            seed: snap.seed,
+            patches: Arc::new(snap.patches),
+            masks: Arc::new(masks),
+            neutral: snap.neutral,
+            patch_pos: snap.patch_pos,
+            bonus_owner: snap.bonus_owner,
+        })
+    }
+}
+
+#[test]
+fn test_snapshot_round_trip() {
+    use crate::moves::Move;
+
+    let mut game = BoardGame::new(42);
+    // 走几步，让拼布、特殊布、纽扣都有变化
+    for _ in 0..12 {
+        let mv = game.legal_moves()[0];
+        game.play(&mv).unwrap();
+    }
+    game.play(&Move::Advance).unwrap();
+
+    let snap = game.snapshot();
+    let json = snap.to_json();
+    let back = Snapshot::from_json(&json).unwrap();
+    assert_eq!(back, snap);
+
+    let restored = BoardGame::from_snapshot(back).unwrap();
+    assert_eq!(restored.snapshot(), snap);
+    assert_eq!(restored.current_player(), game.current_player());
+    assert_eq!(restored.legal_moves(), game.legal_moves());
+}
+
+#[test]
+fn test_snapshot_errors() {
+    let mut snap = BoardGame::new(1).snapshot();
+    snap.version = SNAPSHOT_VERSION + 1;
+    assert!(matches!(
+        Snapshot::from_json(&snap.to_json()),
+        Err(SnapshotError::Version { .. })
+    ));
+
+    let mut snap = BoardGame::new(1).snapshot();
+    snap.patch_pos.pop();
+    assert!(matches!(
+        BoardGame::from_snapshot(snap),
+        Err(SnapshotError::Invalid(_))
+    ));
+
+    assert!(matches!(
+        Snapshot::from_json("{}"),
+        Err(SnapshotError::Json(_))
+    ));
+}
+
+#[test]
+fn test_snapshot_invalid_fields() {
+    use crate::moves::Move;
+
+    // 0号买了一块拼布，1号前进拿到了 19 的特殊布还没放
+    let mut game = BoardGame::new(3);
+    let mv = game
+        .legal_moves()
+        .into_iter()
+        .find(|m| matches!(m, Move::Put { .. }))
+        .unwrap();
+    game.play(&mv).unwrap();
+    game.players[0].pos_idx = 20;
+    game.play(&Move::Advance).unwrap();
+    assert_eq!(game.pending_special, vec![1]);
+    let good = game.snapshot();
+    assert!(BoardGame::from_snapshot(good.clone()).is_ok());
+
+    let rejects = |edit: &dyn Fn(&mut Snapshot)| {
+        let mut snap = good.clone();
+        edit(&mut snap);
+        matches!(BoardGame::from_snapshot(snap), Err(SnapshotError::Invalid(_)))
+    };
+    // 时间板越界
+    assert!(rejects(&|s| s.players[0].pos_idx = TIME_TRACK_END + 1));
+    // 特殊布在拼布板外
+    assert!(rejects(&|s| s.special_pos.push((0, (QUILT_SIZE, 0)))));
+    // 摆放出界
+    assert!(rejects(&|s| {
+        let p = s.patch_pos.iter_mut().flatten().next().unwrap();
+        p.offset = (QUILT_SIZE - 1, QUILT_SIZE - 1);
+    }));
+    // 拼布板和放下的拼布对不上
+    assert!(rejects(&|s| s.players[1].quilt.set(8, 8)));
+    assert!(rejects(&|s| s.players[0].quilt = Quilt::default()));
+    // 特殊布还没放，时间板上又把它放回去了
+    assert!(rejects(&|s| s.special_patches.push(19)));
+    // 拿到的特殊布比走过的多
+    assert!(rejects(&|s| s.pending_special.push(0)));
+    // 没有人走过，却有没放的特殊布
+    assert!(rejects(&|s| {
+        s.players[0].pos_idx = 0;
+        s.players[1].pos_idx = 0;
+        s.special_patches = SPECIAL_PATCH_POS.to_vec();
+    }));
+}
@@ -0,0 +1,207 @@
+use crate::{
+    bitboard::Quilt,
+    board::BoardGame,
+    polyomino::Orientation,
+    score::BONUS_SIZE,
+};
+
+// 摆放求解: 给一块拼布在某个玩家拼布板上所有合法的摆放打分排序
+// 只看放完之后拼布板还好不好放: 空洞、孤立格、7x7 进度、还剩的拼布放不放得下
+// 电脑玩家用它挑位置，提示也用它
+
+// 打分的权重，分越高越好
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SolverWeights {
+    // 小到剩下的拼布都塞不进去的空地，每格
+    pub hole: f64,
+    // 四周都被占了的空格
+    pub isolated: f64,
+    // 空地的周长
+    pub perimeter: f64,
+    // 7x7 还没人拿的时候，最满的 7x7 每填一格
+    pub bonus: f64,
+    // 还没买的拼布里放完之后再也放不下的，每块
+    pub blocked: f64,
+    // 只对粗排前几名算 blocked，这一项比较慢
+    pub refine: usize,
+}
+
+impl Default for SolverWeights {
+    fn default() -> Self {
+        Self {
+            hole: 1.0,
+            isolated: 1.5,
+            perimeter: 0.1,
+            bonus: 0.1,
+            blocked: 0.5,
+            refine: 8,
+        }
+    }
+}
+
+// 一个摆放和它的打分明细
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct RankedPlacement {
+    pub orient: Orientation,
+    // (col, row)
+    pub offset: (usize, usize),
+    pub score: f64,
+    pub hole_cells: usize,
+    pub isolated: usize,
+    pub perimeter: usize,
+    pub bonus_fill: usize,
+    // 没精排的是 None
+    pub blocked: Option<usize>,
+}
+
+impl BoardGame {
+    // 拼布 idx 放在 player 拼布板上的所有摆放，分高的在前
+    pub fn rank_placements(
+        &self,
+        idx: usize,
+        player: usize,
+        weights: &SolverWeights,
+    ) -> Vec<RankedPlacement> {
+        let quilt = self.players[player].quilt;
+        let bonus_open = self.bonus_owner.is_none();
+        // 放完之后还可能买的拼布
+        let rest: Vec<usize> = (0..self.patches.len())
+            .filter(|&i| i != idx && self.patch_pos[i].is_none())
+            .collect();
+        // 比最小的拼布还小的空地就是空洞，只能靠特殊布
+        let smallest = rest.iter().map(|&i| self.patches[i].area()).min().unwrap_or(0);
+
+        let mut ranked: Vec<RankedPlacement> = self
+            .placements(idx, player)
+            .map(|p| {
+                let mut q = quilt;
+                q.place(p.mask);
+                let hole_cells = q
+                    .empty_regions()
+                    .iter()
+                    .map(|r| r.count_ones() as usize)
+                    .filter(|&n| n < smallest)
+                    .sum();
+                let bonus_fill = if bonus_open { q.best_square_fill(BONUS_SIZE) } else { 0 };
+                let mut r = RankedPlacement {
+                    orient: p.orient,
+                    offset: p.offset,
+                    score: 0.0,
+                    hole_cells,
+                    isolated: q.isolated(),
+                    perimeter: q.perimeter(),
+                    bonus_fill,
+                    blocked: None,
+                };
+                r.score = weights.score(&r);
+                r
+            })
+            .collect();
+        sort_by_score(&mut ranked);
+
+        // 精排: 前几名再看看会不会把剩下的拼布堵死
+        // 精排只会扣分，没精排的可能反超上来，那就接着排下一批，直到第一名是精排过的
+        if weights.refine == 0 {
+            return ranked;
+        }
+        while ranked.first().is_some_and(|r| r.blocked.is_none()) {
+            for r in ranked.iter_mut().filter(|r| r.blocked.is_none()).take(weights.refine) {
+                let mut q: Quilt = quilt;
+                if let Some(mask) = self.masks[idx].get(r.offset, r.orient) {
+                    q.place(mask);
+                }
+                let blocked = rest
+                    .iter()
+                    .filter(|&&i| self.masks[i].legal(&q).next().is_none())
+                    .count();
+                r.blocked = Some(blocked);
+                r.score = weights.score(r);
+            }
+            sort_by_score(&mut ranked);
+        }
+        ranked
+    }
+}
+
+impl SolverWeights {
+    fn score(&self, r: &RankedPlacement) -> f64 {
+        self.bonus * r.bonus_fill as f64
+            - self.hole * r.hole_cells as f64
+            - self.isolated * r.isolated as f64
+            - self.perimeter * r.perimeter as f64
+            - self.blocked * r.blocked.unwrap_or(0) as f64
+    }
+}
+
+// 稳定排序，同分的保持掩码表里的顺序，结果可复现
+fn sort_by_score(ranked: &mut [RankedPlacement]) {
+    ranked.sort_by(|a, b| b.score.total_cmp(&a.score));
+}
+
+#[test]
+fn test_rank_placements() {
+    use crate::{patches::Patch, polyomino::Polyomino};
+
+    // 只有两块: 2x2 和 1x3
+    let square = Patch::new(Polyomino::from_rows(&["##", "##"]).unwrap());
+    let line = Patch::new(Polyomino::from_rows(&["###"]).unwrap());
+    let game = BoardGame::with_patches(vec![square, line], 0);
+    let w = SolverWeights::default();
+
+    let ranked = game.rank_placements(0, 0, &w);
+    assert_eq!(ranked.len(), game.placements(0, 0).count());
+    assert!(ranked.windows(2).all(|p| p[0].score >= p[1].score));
+    // 2x2 放在角上最好
+    let best = ranked[0];
+    assert!([(0, 0), (7, 0), (0, 7), (7, 7)].contains(&best.offset));
+    assert_eq!(best.hole_cells, 0);
+    assert!(best.blocked.is_some());
+    assert!(ranked.last().unwrap().blocked.is_none());
+
+    let mut game = game;
+    for x in 0..9 {
+        for y in 0..9 {
+            if x >= 3 || y >= 3 {
+                game.players[0].quilt.set(x, y);
+            }
+        }
+    }
+    // 左上只剩 3x3，2x2 放进去剩一个 L 形 5 格，1x3 还放得下
+    let ranked = game.rank_placements(0, 0, &w);
+    assert_eq!(ranked.len(), 4);
+    assert!(ranked.iter().all(|r| r.hole_cells == 0 && r.blocked == Some(0)));
+
+    // 1x3 横着放中间那行，剩两条 1x3，2x2 就放不下了
+    let ranked = game.rank_placements(1, 0, &w);
+    let middle = ranked
+        .iter()
+        .find(|r| r.offset == (0, 1) && r.orient == Orientation::default())
+        .unwrap();
+    assert_eq!(middle.blocked, Some(1));
+    assert!(ranked[0].blocked == Some(0));
+}
+
+#[test]
+fn test_rank_placements_top_refined() {
+    use crate::bot::{Bot, GreedyBot};
+
+    // 每次只精排一个，最容易被没精排的反超
+    let w = SolverWeights {
+        refine: 1,
+        ..SolverWeights::default()
+    };
+    let mut bot = GreedyBot::default();
+    for seed in 0..4 {
+        let mut game = BoardGame::new(seed);
+        while let Some(mv) = bot.choose(&game) {
+            if game.pending_special.is_empty() {
+                let player = game.current_player();
+                for idx in game.buyable_patches() {
+                    let ranked = game.rank_placements(idx, player, &w);
+                    assert!(ranked[0].blocked.is_some());
+                }
+            }
+            game.play(&mv).unwrap();
+        }
+    }
+}
@@ -444,7 +444,7 @@ async fn request_new_jwt() -> Result<JwtRsp, String> {
 }
 
 // 获取localStorage
-fn get_local_storage() -> Result<Storage, String> {
+pub fn get_local_storage() -> Result<Storage, String> {
     window()
         .ok_or("无法获取window对象")?
         .local_storage()
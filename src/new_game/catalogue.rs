@@ -0,0 +1,65 @@
+use bevy::{
+    asset::{AssetLoader, LoadContext, io::Reader},
+    prelude::*,
+};
+
+use engine_lib::{CatalogueError, Patch, new_patches, parse_catalogue};
+
+use crate::ui::get_asset_path;
+
+// 从数据文件读出来、校验过的拼布目录
+#[derive(Asset, TypePath, Debug)]
+pub struct PatchCatalogue {
+    pub patches: Vec<Patch>,
+}
+
+#[derive(Resource)]
+pub struct CatalogueHandle(pub Handle<PatchCatalogue>);
+
+#[derive(Default, TypePath)]
+pub struct PatchCatalogueLoader;
+
+impl AssetLoader for PatchCatalogueLoader {
+    type Asset = PatchCatalogue;
+    type Settings = ();
+    type Error = CatalogueError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = vec![];
+        reader
+            .read_to_end(&mut bytes)
+            .await
+            .map_err(CatalogueError::Io)?;
+        let json = String::from_utf8_lossy(&bytes);
+        let patches = parse_catalogue(&json)?;
+        Ok(PatchCatalogue { patches })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["json"]
+    }
+}
+
+pub fn load_catalogue_res(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let handle = asset_server.load(get_asset_path("patches.json"));
+    commands.insert_resource(CatalogueHandle(handle));
+}
+
+// 拼布目录还没加载好或者加载失败，就用引擎内置的
+pub fn current_patches(
+    handle: &CatalogueHandle,
+    catalogues: &Assets<PatchCatalogue>,
+) -> Vec<Patch> {
+    match catalogues.get(&handle.0) {
+        Some(c) => c.patches.clone(),
+        None => {
+            warn!("patch catalogue not loaded, use built-in");
+            new_patches()
+        }
+    }
+}
@@ -1,7 +1,12 @@
-use bevy::prelude::*;
+use bevy::{input::mouse::AccumulatedMouseScroll, prelude::*};
+use engine_lib::{BoardGame, Move};
 
 use crate::new_game::{
-    game_state::{BoardGame, ChessBoardProperty, InteractiveInfo},
+    computer::{ComputerPlayer, is_computer_turn, redo_move, undo_move},
+    game_state::{BoardGameRes, ChessBoardProperty, InteractiveInfo, clear_choosing},
+    hint::HINT_COLOR,
+    notice::Notices,
+    replay::ReplayRes,
     patches::{ShapeChooseMark, inner_handle_query_entity_error},
 };
 
@@ -14,22 +19,76 @@ pub struct PreSelectDrawer;
 pub struct PutShapeDrawer;
 
 // 给棋盘的格子标记位置
-#[derive(Component)]
+#[derive(Component, Clone, Copy)]
 pub struct BlockInfo {
+    // 属于哪个玩家的拼布板
+    pub player: usize,
     pub col: usize,
     pub row: usize,
 }
 
+// 玩家拼布板的中心 x 坐标: 0号在右边 1号在左边
+pub fn quilt_pos_x(player: usize) -> f32 {
+    if player == 0 { 7.0 * 60.0 } else { -7.0 * 60.0 }
+}
+
+// 点到 (col, row) 时要执行的行动
+// 有没放的特殊布就放特殊布，否则放选中的拼布
+// 只能放在当前玩家自己的拼布板上
+fn move_at(board: &BoardGame, int_r: &InteractiveInfo, bi: &BlockInfo) -> Option<Move> {
+    if bi.player != board.current_player() {
+        return None;
+    }
+    let offset = (bi.col, bi.row);
+    if !board.pending_special.is_empty() {
+        return Some(Move::PutSpecial { offset });
+    }
+    int_r.choosing_shape.map(|idx| Move::Put {
+        idx,
+        offset,
+        orient: int_r.choosing_shape_orient,
+    })
+}
+
+// 行动会占据的格子
+fn move_cells(board: &BoardGame, mv: &Move) -> Vec<(isize, isize)> {
+    match *mv {
+        Move::Put {
+            idx,
+            offset,
+            orient,
+        } => board.patches[idx].get_pos((offset.0 as isize, offset.1 as isize), orient),
+        Move::PutSpecial { offset } => vec![(offset.0 as isize, offset.1 as isize)],
+        Move::Advance => vec![],
+    }
+}
+
+fn move_color(mv: &Move, preview: bool) -> Color {
+    match (mv, preview) {
+        (Move::PutSpecial { .. }, true) => Color::srgb(0.8, 0.6, 0.3),
+        (Move::PutSpecial { .. }, false) => Color::srgb(0.6, 0.4, 0.2),
+        (_, true) => Color::srgb(0.0, 0.0, 1.0),
+        (_, false) => Color::srgb(1.0, 0.0, 0.0),
+    }
+}
+
 fn board_on_click(
     on: On<Pointer<Click>>,
     query: Query<&BlockInfo>,
     mut int_r: ResMut<InteractiveInfo>,
-    mut board: ResMut<BoardGame>,
+    mut board: ResMut<BoardGameRes>,
     psd: Single<Entity, With<PreSelectDrawer>>,
-    put_shape_drawer: Single<Entity, With<PutShapeDrawer>>,
     mut commands: Commands,
     mut scm: Query<&mut Visibility, With<ShapeChooseMark>>,
+    mut notices: ResMut<Notices>,
+    time: Res<Time>,
+    replay: Option<Res<ReplayRes>>,
+    computer: Option<Res<ComputerPlayer>>,
 ) {
+    // 回放的时候、轮到电脑的时候不能走子
+    if replay.is_some() || is_computer_turn(&board, computer.as_deref()) {
+        return;
+    }
     match query.get(on.event().entity) {
         Err(err) => {
             inner_handle_query_entity_error(err);
@@ -37,67 +96,106 @@ fn board_on_click(
         Ok(bi) => {
             // 执行放置
 
-            // 校验 选没选
-            if int_r.choosing_shape.is_none() {
+            // 校验 选没选 点的是不是自己的拼布板
+            let Some(mv) = move_at(board.as_ref(), int_r.as_ref(), bi) else {
                 // 没选中: 结束
-                warn!("not chose shape");
+                warn!("not chose shape or not your quilt");
                 return;
+            };
+
+            // 校验并放置
+            match board.play(&mv) {
+                Ok(events) => notices.push_events(&events, time.elapsed_secs_f64()),
+                Err(e) => {
+                    warn!("cant put: {:?}", e);
+                    return;
+                }
             }
 
-            // 如果选中
-            let idx = int_r.choosing_shape.unwrap();
+            // 前端记录要清除，已放置的形状交给 redraw_put_shapes 按局面重画
+            clear_choosing(&mut commands, psd.entity(), &mut int_r, &mut scm);
+        }
+    }
+}
 
-            // 校验能放
-            if !board.can_put(idx, (bi.col, bi.row), int_r.choosing_shape_dir.clone()) {
-                warn!("cant put ");
-                return;
-            }
+// 按局面重画所有已放置的拼布和特殊布，悔棋之后也不用一块块去删
+pub fn redraw_put_shapes(
+    board: Res<BoardGameRes>,
+    put_shape_drawer: Single<Entity, With<PutShapeDrawer>>,
+    mut commands: Commands,
+) {
+    let drawer = put_shape_drawer.into_inner();
+    commands.entity(drawer).despawn_children();
 
-            // 放置
-            board.put(idx, &bi, int_r.choosing_shape_dir.clone());
-
-            // 前端记录要清除
-            // 1 psd
-            commands.entity(psd.entity()).despawn_children();
-            // 2 int_resource
-            int_r.choosing_shape = None;
-            // 3 marker
-            for mut v in scm.iter_mut() {
-                *v = Visibility::Hidden;
-            }
+    for (idx, p) in board.patch_pos.iter().enumerate() {
+        let Some(p) = p else {
+            continue;
+        };
+        let mv = Move::Put {
+            idx,
+            offset: p.offset,
+            orient: p.orient,
+        };
+        draw_shape(
+            p.player,
+            &move_cells(board.as_ref(), &mv),
+            &mut commands,
+            drawer,
+            move_color(&mv, false),
+        );
+    }
+    for &(player, offset) in board.special_pos.iter() {
+        let mv = Move::PutSpecial { offset };
+        draw_shape(
+            player,
+            &move_cells(board.as_ref(), &mv),
+            &mut commands,
+            drawer,
+            move_color(&mv, false),
+        );
+    }
+}
 
-            // 前端放置
-            let color = Color::srgb(1.0, 0.0, 0.0);
-            draw_shape(
-                board.as_ref(),
-                idx,
-                bi,
-                int_r.as_mut(),
-                &mut commands,
-                put_shape_drawer.into_inner(),
-                color,
-            );
-        }
+// Ctrl+Z 撤销，Ctrl+Y / Ctrl+Shift+Z 重做
+pub fn undo_input(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut board: ResMut<BoardGameRes>,
+    mut int_r: ResMut<InteractiveInfo>,
+    psd: Single<Entity, With<PreSelectDrawer>>,
+    mut scm: Query<&mut Visibility, With<ShapeChooseMark>>,
+    computer: Option<Res<ComputerPlayer>>,
+    mut commands: Commands,
+) {
+    if !keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+        return;
+    }
+    let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    let redo = keys.just_pressed(KeyCode::KeyY) || (shift && keys.just_pressed(KeyCode::KeyZ));
+    let computer = computer.as_deref();
+    let changed = if redo {
+        redo_move(&mut board, computer)
+    } else if keys.just_pressed(KeyCode::KeyZ) {
+        undo_move(&mut board, computer)
+    } else {
+        return;
+    };
+    if changed.is_some() {
+        clear_choosing(&mut commands, psd.entity(), &mut int_r, &mut scm);
     }
 }
 
 fn draw_shape(
-    board: &BoardGame,
-    idx: usize,
-    bi: &BlockInfo,
-    int_r: &InteractiveInfo,
+    player: usize,
+    cells: &[(isize, isize)],
     commands: &mut Commands,
     psd: Entity,
     color: Color,
 ) {
     let width = 6.0 * 120.0; // 棋盘外边框的长度
     let square_size = width / 9.0; // 9个格子
-    let cbp_pos_x = 7.0 * 60.0;
+    let cbp_pos_x = quilt_pos_x(player);
     let cbp_pos_y = 0.0;
-    for pos in board.patches[idx].get_pos(
-        (bi.col as isize, bi.row as isize),
-        int_r.choosing_shape_dir.clone(),
-    ) {
+    for pos in cells {
         let x = pos.0 as f32 * square_size + square_size / 2.0 + cbp_pos_x - width / 2.0;
         let y = pos.1 as f32 * square_size + square_size / 2.0 + cbp_pos_y - width / 2.0;
         let t = commands
@@ -115,50 +213,92 @@ fn draw_shape(
     }
 }
 
+// 只记下鼠标所在的格子，预览交给 update_preview 去画
 fn board_on_hover(
     on: On<Pointer<Over>>,
     query: Query<&BlockInfo>,
-    int_r: Res<InteractiveInfo>,
-    board: Res<BoardGame>,
-    psd: Single<Entity, With<PreSelectDrawer>>,
-    mut commands: Commands,
+    mut int_r: ResMut<InteractiveInfo>,
 ) {
     match query.get(on.event().entity) {
         Err(err) => {
             inner_handle_query_entity_error(err);
         }
         Ok(bi) => {
-            // 执行渲染：
+            int_r.hovering = Some(*bi);
+        }
+    }
+}
 
-            // 先清掉原先的
-            commands.entity(psd.entity()).despawn_children();
+// 鼠标所在格子、选中的拼布、方向、局面 任何一个变了都重画预览
+pub fn update_preview(
+    int_r: Res<InteractiveInfo>,
+    board: Res<BoardGameRes>,
+    psd: Single<Entity, With<PreSelectDrawer>>,
+    mut commands: Commands,
+) {
+    if !int_r.is_changed() && !board.is_changed() {
+        return;
+    }
 
-            if int_r.choosing_shape.is_none() {
-                // 没选中: 结束
-                warn!("not chose shape");
-                return;
-            }
-            // 如果选中
-            let idx = int_r.choosing_shape.unwrap();
+    // 执行渲染：
 
-            // 校验能放
-            if !board.can_put(idx, (bi.col, bi.row), int_r.choosing_shape_dir.clone()) {
-                warn!("cant put ");
-                return;
-            }
+    // 先清掉原先的
+    commands.entity(psd.entity()).despawn_children();
 
-            // 渲染
-            let color = Color::srgb(0.0, 0.0, 1.0);
-            draw_shape(
-                board.as_ref(),
-                idx,            // shape
-                bi,             // offset
-                int_r.as_ref(), // direction
-                &mut commands,
-                psd.into_inner(), // drawer father
-                color,
-            );
-        }
+    // 推荐的位置，局面变了不合法了就不画
+    if let Some(hint) = &int_r.hint
+        && board.check_move(&hint.mv).is_ok()
+    {
+        draw_shape(
+            board.current_player(),
+            &move_cells(board.as_ref(), &hint.mv),
+            &mut commands,
+            psd.entity(),
+            HINT_COLOR,
+        );
+    }
+
+    let Some(bi) = int_r.hovering else {
+        return;
+    };
+
+    // 选中的拼布或者要放的特殊布
+    let Some(mv) = move_at(board.as_ref(), int_r.as_ref(), &bi) else {
+        // 没选中: 结束
+        return;
+    };
+
+    // 校验能放
+    if board.check_move(&mv).is_err() {
+        return;
+    }
+
+    // 渲染
+    draw_shape(
+        bi.player,
+        &move_cells(board.as_ref(), &mv),
+        &mut commands,
+        psd.into_inner(), // drawer father
+        move_color(&mv, true),
+    );
+}
+
+// 旋转选中的拼布: Q 逆时针，E / R 顺时针，滚轮上下，F 翻面
+pub fn rotate_input(
+    keys: Res<ButtonInput<KeyCode>>,
+    scroll: Res<AccumulatedMouseScroll>,
+    mut int_r: ResMut<InteractiveInfo>,
+) {
+    if int_r.choosing_shape.is_none() {
+        return;
+    }
+    if keys.any_just_pressed([KeyCode::KeyE, KeyCode::KeyR]) || scroll.delta.y < 0.0 {
+        int_r.choosing_shape_orient = int_r.choosing_shape_orient.next();
+    } else if keys.just_pressed(KeyCode::KeyQ) || scroll.delta.y > 0.0 {
+        int_r.choosing_shape_orient = int_r.choosing_shape_orient.prev();
+    }
+    if keys.just_pressed(KeyCode::KeyF) {
+        int_r.choosing_shape_orient = int_r.choosing_shape_orient.flipped();
     }
 }
 
@@ -183,7 +323,11 @@ pub fn spawn_chessboard(commands: &mut Commands, cbp: ChessBoardProperty) {
                         ..Default::default()
                     },
                     Transform::from_xyz(x, y, 0.0),
-                    BlockInfo { row, col },
+                    BlockInfo {
+                        player: cbp.player,
+                        row,
+                        col,
+                    },
                     Pickable::default(),
                 ))
                 .observe(board_on_hover)
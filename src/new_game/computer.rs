@@ -0,0 +1,212 @@
+use bevy::prelude::*;
+#[cfg(not(target_arch = "wasm32"))]
+use bevy::tasks::{AsyncComputeTaskPool, Task, block_on, futures_lite::future};
+use engine_lib::{Bot, Difficulty, GreedyBot, MctsConfig, Move};
+#[cfg(not(target_arch = "wasm32"))]
+use engine_lib::MctsBot;
+#[cfg(target_arch = "wasm32")]
+use engine_lib::{Mcts, mcts::HeuristicRollout};
+#[cfg(target_arch = "wasm32")]
+use {bevy::platform::time::Instant, std::time::Duration};
+
+use crate::new_game::{game_state::BoardGameRes, notice::Notices};
+
+// 电脑玩家在棋谱里的名字，继续存档时靠它认出是人机对局
+pub const COMPUTER_NAME: &str = "computer";
+
+// 电脑走子前停一下，让人看清楚上一步
+const COMPUTER_DELAY_SECS: f32 = 0.6;
+
+// wasm 上没有线程，每帧最多想这么久，不然画面会卡
+#[cfg(target_arch = "wasm32")]
+const MCTS_FRAME_BUDGET: Duration = Duration::from_millis(8);
+
+// 电脑的水平，开始界面选
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum ComputerLevel {
+    #[default]
+    Greedy,
+    Mcts(Difficulty),
+}
+
+impl ComputerLevel {
+    pub const ALL: [ComputerLevel; 4] = [
+        ComputerLevel::Greedy,
+        ComputerLevel::Mcts(Difficulty::Easy),
+        ComputerLevel::Mcts(Difficulty::Normal),
+        ComputerLevel::Mcts(Difficulty::Hard),
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ComputerLevel::Greedy => "greedy",
+            ComputerLevel::Mcts(Difficulty::Easy) => "easy",
+            ComputerLevel::Mcts(Difficulty::Normal) => "normal",
+            ComputerLevel::Mcts(Difficulty::Hard) => "hard",
+        }
+    }
+
+    // 写进棋谱的名字，例如 "computer (normal)"
+    pub fn player_name(self) -> String {
+        format!("{} ({})", COMPUTER_NAME, self.name())
+    }
+
+    pub fn from_player_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|l| l.player_name() == name)
+    }
+}
+
+// 正在想的一步
+enum Thinking {
+    // 本地在后台线程上算
+    #[cfg(not(target_arch = "wasm32"))]
+    Task(Task<Option<Move>>),
+    // wasm 上每帧跑一会，到了 time_limit_ms 就不再想
+    #[cfg(target_arch = "wasm32")]
+    Steps(Box<Mcts>, HeuristicRollout, Option<Instant>),
+    Done(Option<Move>),
+}
+
+// 人机对局里的电脑玩家
+#[derive(Resource)]
+pub struct ComputerPlayer {
+    pub player: usize,
+    pub level: ComputerLevel,
+    // mcts 每次想完换个 seed
+    config: MctsConfig,
+    thinking: Option<Thinking>,
+    timer: Timer,
+}
+
+impl ComputerPlayer {
+    pub fn new(player: usize, level: ComputerLevel) -> Self {
+        let config = match level {
+            ComputerLevel::Mcts(d) => d.config(),
+            ComputerLevel::Greedy => MctsConfig::default(),
+        };
+        Self {
+            player,
+            level,
+            config,
+            thinking: None,
+            timer: Timer::from_seconds(COMPUTER_DELAY_SECS, TimerMode::Once),
+        }
+    }
+
+    // 开始想这一步，贪心很快直接算完
+    fn start(&mut self, board: &BoardGameRes) -> Thinking {
+        let ComputerLevel::Mcts(_) = self.level else {
+            return Thinking::Done(GreedyBot::default().choose(board));
+        };
+        let config = self.config;
+        self.config.seed = self.config.seed.wrapping_add(1);
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let game = board.game.clone();
+            let task = AsyncComputeTaskPool::get()
+                .spawn(async move { MctsBot::with_config(config).choose(&game) });
+            Thinking::Task(task)
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            let deadline = config
+                .time_limit_ms
+                .map(|ms| Instant::now() + Duration::from_millis(ms));
+            Thinking::Steps(
+                Box::new(Mcts::new(&board.game, config)),
+                HeuristicRollout::default(),
+                deadline,
+            )
+        }
+    }
+
+    // 接着想，想完了返回 Done
+    fn poll(thinking: Thinking) -> Thinking {
+        match thinking {
+            #[cfg(not(target_arch = "wasm32"))]
+            Thinking::Task(mut task) => match block_on(future::poll_once(&mut task)) {
+                Some(mv) => Thinking::Done(mv),
+                None => Thinking::Task(task),
+            },
+            #[cfg(target_arch = "wasm32")]
+            Thinking::Steps(mut search, mut rollout, deadline) => {
+                // 一次只跑一个模拟，按时间停
+                let frame_end = Instant::now() + MCTS_FRAME_BUDGET;
+                loop {
+                    search.run(&mut rollout, 1);
+                    let now = Instant::now();
+                    if search.is_done() || deadline.is_some_and(|d| now >= d) {
+                        return Thinking::Done(search.best_move());
+                    }
+                    if now >= frame_end {
+                        return Thinking::Steps(search, rollout, deadline);
+                    }
+                }
+            }
+            done => done,
+        }
+    }
+}
+
+// 现在是不是轮到电脑
+pub fn is_computer_turn(board: &BoardGameRes, computer: Option<&ComputerPlayer>) -> bool {
+    computer.is_some_and(|c| !board.is_game_over() && board.current_player() == c.player)
+}
+
+// 轮到电脑就开始想，想完并且等够了时间再走
+// 中途悔棋回到人的回合，想了一半的就扔掉
+pub fn computer_turn(
+    time: Res<Time>,
+    mut computer: ResMut<ComputerPlayer>,
+    mut board: ResMut<BoardGameRes>,
+    mut notices: ResMut<Notices>,
+) {
+    if !is_computer_turn(&board, Some(&*computer)) {
+        computer.timer.reset();
+        computer.thinking = None;
+        return;
+    }
+    computer.timer.tick(time.delta());
+
+    let thinking = match computer.thinking.take() {
+        Some(t) => t,
+        None => computer.start(&board),
+    };
+    let thinking = ComputerPlayer::poll(thinking);
+    let Thinking::Done(mv) = thinking else {
+        computer.thinking = Some(thinking);
+        return;
+    };
+    if !computer.timer.is_finished() {
+        computer.thinking = Some(Thinking::Done(mv));
+        return;
+    }
+    computer.timer.reset();
+
+    let Some(mv) = mv else {
+        return;
+    };
+    info!("{} plays {}", computer.level.name(), board.move_notation(&mv));
+    match board.play(&mv) {
+        Ok(events) => notices.push_events(&events, time.elapsed_secs_f64()),
+        Err(e) => warn!("computer move {:?} fail: {:?}", mv, e),
+    }
+}
+
+// 人机对局里悔棋: 连电脑的那步一起退回到人的回合
+pub fn undo_move(board: &mut BoardGameRes, computer: Option<&ComputerPlayer>) -> Option<Move> {
+    let mv = board.undo()?;
+    while is_computer_turn(board, computer) && board.history.can_undo() {
+        board.undo();
+    }
+    Some(mv)
+}
+
+pub fn redo_move(board: &mut BoardGameRes, computer: Option<&ComputerPlayer>) -> Option<Move> {
+    let mv = board.redo()?;
+    while is_computer_turn(board, computer) && board.history.can_redo() {
+        board.redo();
+    }
+    Some(mv)
+}
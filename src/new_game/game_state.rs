@@ -1,14 +1,28 @@
-use bevy::{math::U8Vec2, prelude::*};
+use std::ops::{Deref, DerefMut};
+
+use bevy::prelude::*;
 use bevy_egui::{
     EguiContexts, EguiTextureHandle, EguiUserTextures,
     egui::{self, Align2, Id, vec2},
 };
 
+use engine_lib::{
+    BoardGame, GameEvent, GameRecord, History, Move, MoveError, Orientation,
+    board::TIME_TRACK_END,
+};
+
 use crate::{
     game::WIDTH_BASE,
     new_game::{
-        chessboard::{spawn_chessboard, BlockInfo, PreSelectDrawer, PutShapeDrawer},
-        patches::{new_patches, spawn_patches, Patch},
+        catalogue::{CatalogueHandle, PatchCatalogue, current_patches},
+        computer::{ComputerLevel, ComputerPlayer, is_computer_turn, redo_move, undo_move},
+        chessboard::{quilt_pos_x, spawn_chessboard, BlockInfo, PreSelectDrawer, PutShapeDrawer},
+        event::PatchChoosedEvent,
+        hint::Hint,
+        notice::Notices,
+        patches::{ShapeChooseMark, spawn_patches},
+        replay::{RecordImport, ReplayRes, import_record},
+        save::{GameSetup, HasSavedGame, LastRecord, restore_saved_game, today},
     },
     ui::{get_asset_path, my_button, HelloUiTextures},
 };
@@ -22,178 +36,143 @@ pub enum GameState {
     InGame,
 }
 
-enum BoardType {
-    // Yellow,
-    Blue,
-}
-enum TimeBoardType {
-    // Circle,
-    Square,
-}
-
-struct Player {
-    // 玩家存款 5元
-    money: usize,
-    // 玩家指示物 位置
-    pos_idx: usize,
-    // move tick 表示在哪个tick执行了移动
-    last_move_tick: usize,
-}
-
-#[derive(Clone)]
-pub enum ShapeDirection {
-    East,
-    South,
-    West,
-    North,
-}
 #[derive(Resource)]
 pub struct InteractiveInfo {
     pub choosing_shape: Option<usize>,
-    pub choosing_shape_dir: ShapeDirection,
+    pub choosing_shape_orient: Orientation,
+    // 鼠标所在的格子
+    pub hovering: Option<BlockInfo>,
+    // 点了提示之后推荐的一步
+    pub hint: Option<Hint>,
 }
 
+// 规则状态都在 engine_lib::BoardGame 里，这里只多挂一个 root entity
 #[derive(Resource)]
-pub struct BoardGame {
+pub struct BoardGameRes {
     // root component
     root_entity: Entity,
 
-    // 每个玩家的拼布图版的样式
-    board_type: BoardType,
-
-    // 中央时间板的样式
-    time_board_type: TimeBoardType,
-
-    // 中央银行的存款 32 + 12 * 5 + 5 * 10 + 1 * 20
-    bank_money: usize,
-
-    // player // 53的位置结束
-    players: [Player; 2],
+    pub game: BoardGame,
+    // 悔棋
+    pub history: History,
+    // 开局的局面，记棋谱用；从没有棋谱的存档继续时不知道开局，是 None
+    pub start: Option<BoardGame>,
+    // 写进棋谱的玩家名字
+    pub player_names: [String; 2],
+}
 
-    // 标识当前move的 tick
-    global_move_tick: usize,
+impl BoardGameRes {
+    // 走子都从这里过，记进悔棋历史
+    pub fn play(&mut self, mv: &Move) -> Result<Vec<GameEvent>, MoveError> {
+        self.history.play(&mut self.game, mv)
+    }
 
-    // 当前行动的玩家 idx
-    // player: usize,
+    pub fn undo(&mut self) -> Option<Move> {
+        self.history.undo(&mut self.game)
+    }
 
-    // 特殊布的位置 19 25 31 43 49 反着放 pop尾部
-    special_patches: Vec<usize>,
+    pub fn redo(&mut self) -> Option<Move> {
+        self.history.redo(&mut self.game)
+    }
 
-    // 棋盘上纽扣的位置 4 10 16 22 28 34 40 46 52
-    button_pos: [usize; 9],
+    // 开局到现在的棋谱
+    pub fn record(&self) -> Option<GameRecord> {
+        let start = self.start.as_ref()?;
+        let mut record = GameRecord::from_moves(start, self.history.moves());
+        record.players = self.player_names.clone();
+        record.date = today();
+        record.set_result(&self.game);
+        Some(record)
+    }
+}
 
-    // 拼布的随机初始化
-    pub patches: Vec<Patch>,
-    // 拼布放置的位置
-    patch_pos: Vec<Option<U8Vec2>>,
-    // 拼布占据的格子
-    patch_occ: Vec<Vec<bool>>,
-    // 77 板块
+impl Deref for BoardGameRes {
+    type Target = BoardGame;
+    fn deref(&self) -> &Self::Target {
+        &self.game
+    }
 }
 
+impl DerefMut for BoardGameRes {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.game
+    }
+}
 
 pub struct ChessBoardProperty {
     pub root_entity: Entity,
+    // 属于哪个玩家
+    pub player: usize,
     pub pos_x: f32,
     pub pos_y: f32,
     pub color1: Color,
     pub color2: Color,
 }
 
-impl BoardGame {
-    pub fn put(&mut self, idx: usize, offset: &BlockInfo, dir: ShapeDirection) {
-        // 中央银行存款要扣除给到 玩家 todo
-        // 玩家存款要根据patch 更新 todo
-
-        // move tick 更新 todo
-        // 导致的特殊布的更新 todo
-
-        // 导致的纽扣更新 todo
-
-        // 拼布放置的位置更新
-        self.patch_pos[idx] = Some(U8Vec2 {
-            x: offset.col as u8,
-            y: offset.row as u8,
-        });
-        // 占据的格子的更新
-        self.patches[idx].get_pos((offset.col as isize, offset.row as isize), dir).iter().for_each(|&(x,y)|{
-            if x < 0 || x >= 9 || y < 0 || y >= 9 {
-                return;
-            }
-            self.patch_occ[x as usize][y as usize] = true;
-        });
-    }
-    pub fn can_put(&self, idx: usize, offset: (usize, usize), dir: ShapeDirection) -> bool {
-        // 校验 idx 范围
-        if idx > self.patches.len() {
-            warn!("can put fail: {} > {}", idx, self.patches.len());
-            return false;
-        }
-
-        // 校验是否放置
-        if self.patch_pos[idx].is_some() {
-            warn!("can put fail: {} already put", idx);
-            return false;
-        }
-
-        // 校验交叉
-        let offset = (offset.0 as isize, offset.1 as isize);
-        if self.patches[idx]
-            .get_pos(offset, dir)
-            .iter()
-            .map(|&(x, y)| {
-                if x < 0 || x >= 9 || y < 0 || y >= 9 {
-                    return true;
-                }
-                self.patch_occ[x as usize][y as usize]
-            })
-            .any(|b| b)
-        {
-            warn!("can put fail: cross {}", idx);
-            return false;
-        }
-        return true;
-    }
-    pub fn new(e: Entity) -> Self {
-        let patches = new_patches();
-        Self {
-            patch_occ: vec![vec![false; 9]; 9],
-            root_entity: e,
-            board_type: BoardType::Blue,
-            time_board_type: TimeBoardType::Square,
-            bank_money: 32 + 12 * 5 + 5 * 10 + 1 * 20,
-            players: [
-                Player {
-                    money: 5,
-                    pos_idx: 0,
-                    last_move_tick: 1,
-                },
-                Player {
-                    money: 5,
-                    pos_idx: 0,
-                    last_move_tick: 0,
-                },
-            ],
-            global_move_tick: 2, // move_tick从2开始计数 谁移动了，谁的last move_tick就设置成global move_tick，之后global_move + 1
-            special_patches: vec![49, 43, 31, 25, 19],
-            button_pos: [4, 10, 16, 22, 28, 34, 40, 46, 52],
-            patch_pos: vec![None; patches.len()],
-            patches: patches,
-        }
-    }
-}
-
 // In game
 // 每次进入game 都初始化一个新的游戏资源
 // 布置sprite 场景
 // 设定好每个sprite 的 事件
 pub fn init_game_resource(
     mut commands: Commands,
+    catalogue: Res<CatalogueHandle>,
+    catalogues: Res<Assets<PatchCatalogue>>,
+    setup: Res<GameSetup>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
 ) {
     let root_entity = commands.spawn(Transform::from_xyz(0.0, 0.0, 0.0)).id();
-    let r = BoardGame::new(root_entity);
+    // 每局随机一个 seed，记在 BoardGame 上，同一个 seed 能复现同一局
+    let seed = getrandom::u64().unwrap_or_else(|e| {
+        warn!("getrandom fail: {:?}", e);
+        0
+    });
+    let patches = current_patches(&catalogue, &catalogues);
+    let game = BoardGame::from_catalogue(patches.clone(), seed);
+    let mut r = BoardGameRes {
+        root_entity,
+        start: Some(game.clone()),
+        game,
+        history: History::default(),
+        player_names: ["player 0".to_string(), "player 1".to_string()],
+    };
+    let mut computer = setup.vs_computer.then_some(setup.computer_level);
+
+    // 继续上次的存档，读不出来就开新局
+    if setup.continue_saved {
+        match restore_saved_game(&patches) {
+            Ok(saved) => {
+                r.start = saved.start;
+                r.game = saved.game;
+                r.history = saved.history;
+                if let Some(players) = saved.players {
+                    computer = ComputerLevel::from_player_name(&players[1]);
+                    r.player_names = players;
+                }
+            }
+            Err(e) => warn!("load saved game fail: {}", e),
+        }
+    }
+
+    // 人机对局，电脑是后手的 1 号
+    if let Some(level) = computer {
+        r.player_names = ["player".to_string(), level.player_name()];
+        commands.insert_resource(ComputerPlayer::new(1, level));
+    }
+
+    // 回放棋谱，从开局开始
+    if let Some(record) = &setup.replay {
+        let start = BoardGame::from_catalogue(patches.clone(), record.seed);
+        match record.replay(start) {
+            Ok(states) => {
+                r.start = Some(states[0].clone());
+                r.game = states[0].clone();
+                commands.insert_resource(ReplayRes::new(record.clone(), states));
+            }
+            Err(e) => warn!("replay record fail: {}", e),
+        }
+    }
+    info!("patches len: {} seed: {}", r.patches.len(), r.seed);
 
     // 放置patches
     let shape = meshes.add(Triangle2d::new(
@@ -204,20 +183,22 @@ pub fn init_game_resource(
     let color = materials.add(Color::linear_rgb(0.0, 1.0, 0.0));
     spawn_patches(&mut commands, &r.patches, root_entity, shape, color);
 
-    // 放置棋盘
+    // 放置棋盘 0号玩家
     let cbp = ChessBoardProperty {
         root_entity,
-        pos_x: 7.0 * 60.0,
+        player: 0,
+        pos_x: quilt_pos_x(0),
         pos_y: 0.0,
         color1: Color::srgb_u8(128, 128, 128),
         color2: Color::srgb_u8(73, 73, 73),
     };
     spawn_chessboard(&mut commands, cbp);
 
-    // 放置棋盘
+    // 放置棋盘 1号玩家
     let cbp = ChessBoardProperty {
         root_entity,
-        pos_x: -7.0 * 60.0,
+        player: 1,
+        pos_x: quilt_pos_x(1),
         pos_y: 0.0,
         color1: Color::srgb_u8(116, 218, 255),
         color2: Color::srgb_u8(78, 208, 255),
@@ -229,7 +210,9 @@ pub fn init_game_resource(
     // 前端交互资源
     commands.insert_resource(InteractiveInfo {
         choosing_shape: None,
-        choosing_shape_dir: ShapeDirection::East,
+        choosing_shape_orient: Orientation::default(),
+        hovering: None,
+        hint: None,
     });
 
     // 用于提示放置位置的Component
@@ -241,10 +224,12 @@ pub fn init_game_resource(
     commands.entity(root_entity).add_child(t);
 }
 
-pub fn del_game_component(mut commands: Commands, res: Res<BoardGame>) {
+pub fn del_game_component(mut commands: Commands, res: Res<BoardGameRes>) {
     let e = res.root_entity;
     commands.entity(e).despawn();
-    commands.remove_resource::<BoardGame>();
+    commands.remove_resource::<BoardGameRes>();
+    commands.remove_resource::<ReplayRes>();
+    commands.remove_resource::<ComputerPlayer>();
 }
 
 
@@ -273,6 +258,12 @@ pub fn hello_ui(
     mut contexts: EguiContexts,
     button_res: Res<HelloUiTextures>,
     mut next_gamestate: ResMut<NextState<GameState>>,
+    mut setup: ResMut<GameSetup>,
+    has_saved: Res<HasSavedGame>,
+    last_record: Res<LastRecord>,
+    mut import: ResMut<RecordImport>,
+    catalogue: Res<CatalogueHandle>,
+    catalogues: Res<Assets<PatchCatalogue>>,
 ) -> Result {
     let ctx = contexts.ctx_mut()?;
     egui::Area::new(Id::new("hello_ui"))
@@ -287,10 +278,261 @@ pub fn hello_ui(
                 );
 
                 if r.clicked() {
+                    *setup = GameSetup::default();
                     next_gamestate.set(GameState::InGame);
                 }
+
+                // 有存档才显示
+                if has_saved.0 {
+                    let r = my_button(
+                        ui,
+                        "continue game",
+                        &button_res.get_textures(),
+                        vec2(WIDTH_BASE, WIDTH_BASE / 2.0),
+                    );
+                    if r.clicked() {
+                        *setup = GameSetup {
+                            continue_saved: true,
+                            ..default()
+                        };
+                        next_gamestate.set(GameState::InGame);
+                    }
+                }
+
+                let r = my_button(
+                    ui,
+                    "play vs computer",
+                    &button_res.get_textures(),
+                    vec2(WIDTH_BASE, WIDTH_BASE / 2.0),
+                );
+                if r.clicked() {
+                    *setup = GameSetup {
+                        vs_computer: true,
+                        computer_level: setup.computer_level,
+                        ..default()
+                    };
+                    next_gamestate.set(GameState::InGame);
+                }
+
+                // 上一局下完的棋谱
+                if let Some(record) = &last_record.0 {
+                    let r = my_button(
+                        ui,
+                        "replay last game",
+                        &button_res.get_textures(),
+                        vec2(WIDTH_BASE, WIDTH_BASE / 2.0),
+                    );
+                    if r.clicked() {
+                        *setup = GameSetup {
+                            replay: Some(record.clone()),
+                            ..default()
+                        };
+                        next_gamestate.set(GameState::InGame);
+                    }
+                }
+            });
+
+            // 电脑的水平
+            ui.horizontal(|ui| {
+                ui.label("computer:");
+                for level in ComputerLevel::ALL {
+                    ui.selectable_value(&mut setup.computer_level, level, level.name());
+                }
+            });
+
+            // 贴进别人分享的棋谱回放
+            ui.collapsing("replay a record", |ui| {
+                ui.add(
+                    egui::TextEdit::multiline(&mut import.text)
+                        .desired_rows(6)
+                        .hint_text("paste a game record here"),
+                );
+                if ui.button("replay record").clicked() {
+                    match import_record(&import.text, &current_patches(&catalogue, &catalogues)) {
+                        Ok(record) => {
+                            import.error = None;
+                            *setup = GameSetup {
+                                replay: Some(record),
+                                ..default()
+                            };
+                            next_gamestate.set(GameState::InGame);
+                        }
+                        Err(e) => import.error = Some(e),
+                    }
+                }
+                if let Some(e) = &import.error {
+                    ui.colored_label(egui::Color32::RED, e);
+                }
             });
         });
 
     Ok(())
 }
+
+// 游戏内的 ui: 时间板进度和当前行动的玩家
+pub fn game_ui(
+    mut contexts: EguiContexts,
+    mut board: ResMut<BoardGameRes>,
+    mut int_r: ResMut<InteractiveInfo>,
+    mut next_gamestate: ResMut<NextState<GameState>>,
+    psd: Single<Entity, With<PreSelectDrawer>>,
+    mut scm: Query<&mut Visibility, With<ShapeChooseMark>>,
+    mut notices: ResMut<Notices>,
+    time: Res<Time>,
+    computer: Option<Res<ComputerPlayer>>,
+    mut commands: Commands,
+) -> Result {
+    let computer = computer.as_deref();
+    let ctx = contexts.ctx_mut()?;
+    egui::Area::new(Id::new("game_ui"))
+        .anchor(Align2::CENTER_TOP, [0.0, 10.0])
+        .show(ctx, |ui| {
+            players_ui(ui, &board);
+
+            ui.label(format!("seed: {}", board.seed));
+
+            // 悔棋
+            ui.horizontal(|ui| {
+                let undo =
+                    ui.add_enabled(board.history.can_undo(), egui::Button::new("undo (Ctrl+Z)"));
+                let redo =
+                    ui.add_enabled(board.history.can_redo(), egui::Button::new("redo (Ctrl+Y)"));
+                let changed = if undo.clicked() {
+                    undo_move(&mut board, computer)
+                } else if redo.clicked() {
+                    redo_move(&mut board, computer)
+                } else {
+                    None
+                };
+                if changed.is_some() {
+                    clear_choosing(&mut commands, psd.entity(), &mut int_r, &mut scm);
+                }
+            });
+
+            if board.is_game_over() {
+                game_over_ui(ui, &board);
+                // 复制棋谱，方便分享
+                if let Some(record) = board.record()
+                    && ui.button("copy record").clicked()
+                {
+                    ui.ctx().copy_text(record.to_text());
+                }
+                if ui.button("back").clicked() {
+                    next_gamestate.set(GameState::HelloUI);
+                }
+            } else {
+                let player = board.current_player();
+                ui.label(format!("current player: {}", board.player_names[player]));
+
+                if is_computer_turn(&board, computer) {
+                    ui.label("computer is thinking...");
+                    return;
+                }
+
+                // 提示: 外圈标出推荐的拼布并选中，拼布板上画出推荐的位置
+                if ui.button("hint").clicked() {
+                    let hint = Hint::suggest(&board);
+                    if let Some(Hint {
+                        mv: Move::Put { idx, orient, .. },
+                        ..
+                    }) = hint
+                    {
+                        int_r.choosing_shape_orient = orient;
+                        commands.trigger(PatchChoosedEvent { patch_idx: idx });
+                    }
+                    int_r.hint = hint;
+                }
+                if let Some(hint) = &int_r.hint {
+                    ui.label(hint.explain(&board));
+                }
+
+                // 先放特殊布
+                if !board.pending_special.is_empty() {
+                    ui.label("place the 1x1 patch on your quilt");
+                    return;
+                }
+
+                // 旋转、翻面选中的拼布
+                ui.horizontal(|ui| {
+                    if ui.button("⟲ (Q)").clicked() {
+                        int_r.choosing_shape_orient = int_r.choosing_shape_orient.prev();
+                    }
+                    let o = int_r.choosing_shape_orient;
+                    ui.label(format!("{:?}{}", o.dir, if o.flip { " flipped" } else { "" }));
+                    if ui.button("⟳ (E)").clicked() {
+                        int_r.choosing_shape_orient = int_r.choosing_shape_orient.next();
+                    }
+                    if ui.button("flip (F)").clicked() {
+                        int_r.choosing_shape_orient = int_r.choosing_shape_orient.flipped();
+                    }
+                });
+
+                // 不买拼布，前进到对手前一格拿纽扣
+                let steps = board.advance_steps(player);
+                if ui.button(format!("advance (+{})", steps)).clicked() {
+                    match board.play(&Move::Advance) {
+                        Ok(events) => notices.push_events(&events, time.elapsed_secs_f64()),
+                        Err(e) => {
+                            warn!("cant advance: {:?}", e);
+                            return;
+                        }
+                    }
+
+                    clear_choosing(&mut commands, psd.entity(), &mut int_r, &mut scm);
+                }
+            }
+        });
+
+    Ok(())
+}
+
+// 两个玩家的时间板位置、纽扣和收入
+pub fn players_ui(ui: &mut egui::Ui, board: &BoardGame) {
+    for (idx, p) in board.players.iter().enumerate() {
+        ui.label(format!(
+            "player {}: {} / {}  money: {}  income: {}",
+            idx, p.pos_idx, TIME_TRACK_END, p.money, p.button_income
+        ));
+    }
+}
+
+// 清掉选中状态: 预览、选中的拼布、绿色三角
+pub fn clear_choosing(
+    commands: &mut Commands,
+    psd: Entity,
+    int_r: &mut InteractiveInfo,
+    scm: &mut Query<&mut Visibility, With<ShapeChooseMark>>,
+) {
+    commands.entity(psd).despawn_children();
+    int_r.choosing_shape = None;
+    int_r.hint = None;
+    for mut v in scm.iter_mut() {
+        *v = Visibility::Hidden;
+    }
+}
+
+// 结束画面: 两个玩家的计分明细
+fn game_over_ui(ui: &mut egui::Ui, board: &BoardGame) {
+    ui.heading("game over");
+    egui::Grid::new("game_over_score").striped(true).show(ui, |ui| {
+        ui.label("");
+        ui.label("buttons");
+        ui.label("empty (-2)");
+        ui.label("7x7 (+7)");
+        ui.label("total");
+        ui.end_row();
+
+        for player in 0..board.players.len() {
+            let s = board.score(player);
+            ui.label(format!("player {}", player));
+            ui.label(s.buttons.to_string());
+            ui.label(s.empty.to_string());
+            ui.label(if s.bonus { "yes" } else { "no" });
+            ui.label(s.total().to_string());
+            ui.end_row();
+        }
+    });
+    if let Some(winner) = board.winner() {
+        ui.label(format!("winner: player {}", winner));
+    }
+}
@@ -0,0 +1,50 @@
+use bevy::prelude::*;
+use engine_lib::{BoardGame, GreedyBot, Move};
+
+// 提示: 用贪心电脑给当前玩家挑一步，外圈标出拼布，拼布板上画出位置
+#[derive(Clone, Debug)]
+pub struct Hint {
+    pub mv: Move,
+    // 估值涨了多少，单位是纽扣
+    pub gain: f64,
+}
+
+// 提示的位置用绿色画，和悬停预览的蓝色区分开
+pub const HINT_COLOR: Color = Color::srgb(0.1, 0.8, 0.3);
+
+impl Hint {
+    // 当前玩家的提示，游戏结束了是 None
+    pub fn suggest(board: &BoardGame) -> Option<Hint> {
+        let bot = GreedyBot::default();
+        let (mv, gain) = *bot.ranked(board).first()?;
+        Some(Hint { mv, gain })
+    }
+
+    // 一句话说明为什么走这步
+    pub fn explain(&self, board: &BoardGame) -> String {
+        let what = match self.mv {
+            Move::Put { idx, .. } => {
+                let p = &board.patches[idx];
+                format!(
+                    "buy patch {} ({} buttons, {} time), income +{}, covers {} squares",
+                    p.id,
+                    p.bt.0,
+                    p.bt.1,
+                    p.button,
+                    p.area()
+                )
+            }
+            Move::Advance => {
+                let steps = board.advance_steps(board.current_player());
+                format!("advance {} squares for {} buttons", steps, steps)
+            }
+            Move::PutSpecial { .. } => "place the 1x1 patch".to_string(),
+        };
+        format!(
+            "hint: {} ({}), expected gain {:+.1}",
+            what,
+            board.move_notation(&self.mv),
+            self.gain
+        )
+    }
+}
@@ -1,16 +1,33 @@
+pub mod catalogue;
 pub mod chessboard;
+pub mod computer;
 pub mod event;
 pub mod game_state;
+pub mod hint;
+pub mod notice;
 pub mod patches;
+pub mod replay;
+pub mod save;
 use bevy::prelude::*;
 use bevy_egui::{EguiPlugin, EguiPrimaryContextPass, egui::vec2};
 
 use crate::{
     game::{HEIGHT, WIDTH},
     new_game::{
+        catalogue::{PatchCatalogue, PatchCatalogueLoader, load_catalogue_res},
+        chessboard::{redraw_put_shapes, rotate_input, undo_input, update_preview},
+        computer::{ComputerPlayer, computer_turn},
         event::observe_patch_choose_event,
         game_state::{
-            GameState, del_game_component, hello_ui, init_game_resource, load_hello_ui_res,
+            BoardGameRes, GameState, del_game_component, game_ui, hello_ui, init_game_resource,
+            load_hello_ui_res,
+        },
+        notice::{Notices, notice_ui},
+        patches::update_market_view,
+        replay::{RecordImport, ReplayRes, replay_input, replay_ui, sync_replay_board},
+        save::{
+            GameSetup, HasSavedGame, LastRecord, autosave_game, refresh_has_saved_game,
+            refresh_last_record,
         },
     },
 };
@@ -32,17 +49,110 @@ impl Plugin for NewGamePlug {
         // ui按钮 ziyuan
         app.add_systems(Startup, load_hello_ui_res);
 
-        // 开始界面的ui
+        // 拼布目录，从 assets/patches.json 读
+        app.init_asset::<PatchCatalogue>()
+            .register_asset_loader(PatchCatalogueLoader);
+        app.add_systems(Startup, load_catalogue_res);
+
+        // 开始界面的ui，进来时看一下有没有存档、上一局的棋谱
+        app.init_resource::<HasSavedGame>()
+            .init_resource::<LastRecord>()
+            .init_resource::<RecordImport>();
+        app.add_systems(
+            OnEnter(GameState::HelloUI),
+            (refresh_has_saved_game, refresh_last_record),
+        );
         app.add_systems(
             EguiPrimaryContextPass,
             hello_ui.run_if(in_state(GameState::HelloUI)),
         );
 
+        // 游戏内的ui
+        app.add_systems(
+            EguiPrimaryContextPass,
+            game_ui
+                .run_if(in_state(GameState::InGame))
+                .run_if(not(resource_exists::<ReplayRes>)),
+        );
+
+        // 回放棋谱: 翻页的 ui 和方向键，翻到的局面换进 BoardGameRes
+        app.add_systems(
+            EguiPrimaryContextPass,
+            replay_ui
+                .run_if(in_state(GameState::InGame))
+                .run_if(resource_exists::<ReplayRes>),
+        );
+        app.add_systems(
+            Update,
+            (replay_input, sync_replay_board.run_if(resource_changed::<ReplayRes>))
+                .chain()
+                .run_if(in_state(GameState::InGame))
+                .run_if(resource_exists::<ReplayRes>),
+        );
+
+        // 结算提示
+        app.init_resource::<Notices>();
+        app.add_systems(
+            EguiPrimaryContextPass,
+            notice_ui.run_if(in_state(GameState::InGame)),
+        );
+
         // 每新开一局就
         // 初始化后端游戏资源数据
         // 初始化前端交互标记资源
         app.add_systems(OnEnter(GameState::InGame), init_game_resource);
 
+        // 每走一步自动存档
+        app.init_resource::<GameSetup>();
+        app.add_systems(
+            Update,
+            autosave_game
+                .run_if(in_state(GameState::InGame))
+                .run_if(not(resource_exists::<ReplayRes>))
+                .run_if(resource_exists_and_changed::<BoardGameRes>),
+        );
+
+        // 外圈拼布的高亮和遮罩
+        app.add_systems(
+            Update,
+            update_market_view
+                .run_if(in_state(GameState::InGame))
+                .run_if(resource_exists_and_changed::<BoardGameRes>),
+        );
+
+        // 已放置的拼布按局面重画
+        app.add_systems(
+            Update,
+            redraw_put_shapes
+                .run_if(in_state(GameState::InGame))
+                .run_if(resource_exists_and_changed::<BoardGameRes>),
+        );
+
+        // 人机对局里电脑走子
+        app.add_systems(
+            Update,
+            computer_turn
+                .run_if(in_state(GameState::InGame))
+                .run_if(resource_exists::<ComputerPlayer>)
+                .run_if(not(resource_exists::<ReplayRes>)),
+        );
+
+        // 悔棋快捷键
+        app.add_systems(
+            Update,
+            undo_input
+                .run_if(in_state(GameState::InGame))
+                .run_if(not(resource_exists::<ReplayRes>)),
+        );
+
+        // 旋转选中的拼布，刷新放置预览
+        app.add_systems(
+            Update,
+            (rotate_input, update_preview)
+                .chain()
+                .run_if(in_state(GameState::InGame)),
+        );
+
         // 删除游戏资源和compnent
         app.add_systems(OnExit(GameState::InGame), del_game_component);
 
@@ -0,0 +1,60 @@
+use bevy::prelude::*;
+use bevy_egui::{
+    EguiContexts,
+    egui::{self, Align2, Id},
+};
+use engine_lib::GameEvent;
+
+// 提示显示的秒数
+const NOTICE_SECS: f64 = 3.0;
+
+// 行动结算的提示 (文字, 过期时间)
+#[derive(Resource, Default)]
+pub struct Notices {
+    pub list: Vec<(String, f64)>,
+}
+
+impl Notices {
+    pub fn push(&mut self, text: String, now: f64) {
+        self.list.push((text, now + NOTICE_SECS));
+    }
+
+    pub fn push_events(&mut self, events: &[GameEvent], now: f64) {
+        for e in events {
+            let text = match e {
+                GameEvent::Advanced { player, buttons } => {
+                    format!("player {} advanced: +{} buttons", player, buttons)
+                }
+                GameEvent::ButtonIncome { player, buttons } => {
+                    format!("player {} income: +{} buttons", player, buttons)
+                }
+                GameEvent::SpecialPatch { player } => {
+                    format!("player {} got a 1x1 patch, place it now", player)
+                }
+                GameEvent::BonusTile { player } => {
+                    format!("player {} filled a 7x7 square: +7", player)
+                }
+            };
+            self.push(text, now);
+        }
+    }
+}
+
+pub fn notice_ui(mut contexts: EguiContexts, mut notices: ResMut<Notices>, time: Res<Time>) -> Result {
+    let now = time.elapsed_secs_f64();
+    notices.list.retain(|(_, expire)| *expire > now);
+    if notices.list.is_empty() {
+        return Ok(());
+    }
+
+    let ctx = contexts.ctx_mut()?;
+    egui::Area::new(Id::new("notice_ui"))
+        .anchor(Align2::RIGHT_TOP, [-10.0, 10.0])
+        .show(ctx, |ui| {
+            for (text, _) in notices.list.iter() {
+                ui.label(text);
+            }
+        });
+
+    Ok(())
+}
@@ -2,14 +2,20 @@ use std::collections::VecDeque;
 
 use bevy::{ecs::query::QueryEntityError, prelude::*};
 
+use engine_lib::Patch;
+
 use crate::{
     game::WIDTH_BASE,
-    new_game::{event::PatchChoosedEvent, game_state::ShapeDirection, generate_color, mid_pos},
+    new_game::{
+        computer::{ComputerPlayer, is_computer_turn},
+        event::PatchChoosedEvent, game_state::BoardGameRes, generate_color, mid_pos,
+        replay::ReplayRes,
+    },
 };
 
 // 展示出的shape对应哪个patch
 #[derive(Component)]
-struct PatchComponent {
+pub struct PatchComponent {
     pub patch_idx: usize,
 }
 
@@ -20,308 +26,10 @@ pub struct ShapeChooseMark {
     pub patch_idx: usize,
 }
 
-pub struct Patch {
-    shape: Vec<usize>,
-    bt: (usize, usize),
-    button: usize,
-}
-impl Patch {
-    pub fn get_pos(&self, offset: (isize, isize), dir: ShapeDirection) -> Vec<(isize, isize)> {
-        match dir {
-            ShapeDirection::East => self
-                .shape
-                .iter()
-                .enumerate()
-                .filter_map(|(idx, has)| {
-                    let idx = idx as isize;
-                    let row = idx / 3;
-                    let col = idx % 3;
-                    if *has == 1 {
-                        let r = (col + offset.0, row + offset.1);
-                        Some(r)
-                    } else {
-                        None
-                    }
-                })
-                .collect(),
-            ShapeDirection::South => self
-                .shape
-                .iter()
-                .enumerate()
-                .filter_map(|(idx, has)| {
-                    let idx = idx as isize;
-                    let row = idx / 3;
-                    let col = idx % 3;
-                    if *has == 1 {
-                        let r = (row + offset.0, -col + offset.1);
-                        Some(r)
-                    } else {
-                        None
-                    }
-                })
-                .collect(),
-            ShapeDirection::West => self
-                .shape
-                .iter()
-                .enumerate()
-                .filter_map(|(idx, has)| {
-                    let idx = idx as isize;
-                    let row = idx / 3;
-                    let col = idx % 3;
-                    if *has == 1 {
-                        let r = (-col + offset.0, -row + offset.1);
-                        Some(r)
-                    } else {
-                        None
-                    }
-                })
-                .collect(),
-            ShapeDirection::North => self
-                .shape
-                .iter()
-                .enumerate()
-                .filter_map(|(idx, has)| {
-                    let idx = idx as isize;
-                    let row = idx / 3;
-                    let col = idx % 3;
-                    if *has == 1 {
-                        let r = (-row + offset.0, col + offset.1);
-                        Some(r)
-                    } else {
-                        None
-                    }
-                })
-                .collect(),
-        }
-    }
-    pub fn new(shape: Vec<usize>) -> Self {
-        Self {
-            shape,
-            bt: (0, 0),
-            button: 0,
-        }
-    }
-}
-
-#[test]
-fn test_get_pos() {
-    let patch = Patch::new(vec![1]);
-    assert_eq!(patch.get_pos((0, 0), ShapeDirection::East), vec![(0, 0)]);
-
-    let patch = Patch::new(vec![1, 1]);
-    assert_eq!(
-        patch.get_pos((0, 0), ShapeDirection::East),
-        vec![(0, 0), (1, 0)]
-    );
-
-    let patch = Patch::new(vec![1, 1, 1]);
-    assert_eq!(
-        patch.get_pos((0, 0), ShapeDirection::East),
-        vec![(0, 0), (1, 0), (2, 0)]
-    );
-
-    let patch = Patch::new(vec![1, 1, 1, 1]);
-    assert_eq!(
-        patch.get_pos((0, 0), ShapeDirection::East),
-        vec![(0, 0), (1, 0), (2, 0), (0, 1)]
-    );
-
-    let patch = Patch::new(vec![1, 1, 1, 1]);
-    assert_eq!(
-        patch.get_pos((0, 0), ShapeDirection::South),
-        vec![(0, 0), (0, -1), (0, -2), (1, 0)]
-    );
-
-    let patch = Patch::new(vec![1, 1, 1, 1]);
-    assert_eq!(
-        patch.get_pos((0, 0), ShapeDirection::West),
-        vec![(0, 0), (-1, 0), (-2, 0), (0, -1)]
-    );
-
-    let patch = Patch::new(vec![1, 1, 1, 1]);
-    assert_eq!(
-        patch.get_pos((2, 2), ShapeDirection::West),
-        vec![(2, 2), (1, 2), (0, 2), (2, 1)]
-    );
-
-    let patch = Patch::new(vec![1, 1, 1, 1]);
-    assert_eq!(
-        patch.get_pos((0, 0), ShapeDirection::North),
-        vec![(0, 0), (0, 1), (0, 2), (-1, 0)]
-    );
-
-}
-
-pub fn new_patches() -> Vec<Patch> {
-    let patches = vec![
-        Patch {
-            shape: vec![1, 1, 0, 0, 1, 0, 0, 1, 0, 0, 1, 1],
-            bt: (1, 2),
-            button: 0,
-        },
-        Patch {
-            shape: vec![0, 1, 0, 1, 1, 1, 0, 1, 0, 0, 1],
-            bt: (0, 3),
-            button: 1,
-        },
-        Patch {
-            shape: vec![0, 0, 1, 0, 1, 1, 1, 1],
-            bt: (10, 4),
-            button: 3,
-        },
-        Patch {
-            shape: vec![1, 0, 0, 1, 1, 0, 1, 0, 0, 1],
-            bt: (3, 4),
-            button: 1,
-        },
-        Patch {
-            shape: vec![1, 1, 0, 0, 1],
-            bt: (3, 1),
-            button: 1,
-        },
-        Patch {
-            shape: vec![1, 1, 1, 0, 1, 0, 1, 1, 1],
-            bt: (2, 3),
-            button: 0,
-        },
-        Patch {
-            shape: vec![0, 1, 0, 1, 1, 0, 1, 1, 0, 1],
-            bt: (4, 2),
-            button: 0,
-        },
-        Patch {
-            shape: vec![1, 1, 1, 1, 1],
-            bt: (2, 2),
-            button: 0,
-        },
-        Patch {
-            shape: vec![0, 1, 1, 1, 1, 0, 0, 1, 1],
-            bt: (3, 6),
-            button: 0,
-        },
-        Patch {
-            shape: vec![1, 1],
-            bt: (2, 1),
-            button: 0,
-        },
-        Patch {
-            shape: vec![1, 1, 0, 1, 0, 0, 1, 0, 0, 1],
-            bt: (10, 3),
-            button: 2,
-        },
-        Patch {
-            shape: vec![0, 1, 0, 1, 1, 1, 0, 1],
-            bt: (5, 4),
-            button: 2,
-        },
-        Patch {
-            shape: vec![1, 1, 1, 0, 1, 0, 0, 1, 0, 0, 1],
-            bt: (7, 2),
-            button: 2,
-        },
-        Patch {
-            shape: vec![0, 1, 0, 1, 1, 0, 0, 1, 1, 0, 1],
-            bt: (2, 1),
-            button: 0,
-        },
-        Patch {
-            shape: vec![1, 1, 0, 1, 1, 1, 0, 0, 1],
-            bt: (8, 6),
-            button: 3,
-        },
-        Patch {
-            shape: vec![1, 0, 0, 1, 1, 0, 1, 1, 0, 1],
-            bt: (7, 4),
-            button: 2,
-        },
-        Patch {
-            shape: vec![1, 1, 0, 0, 1, 0, 0, 1],
-            bt: (4, 6),
-            button: 2,
-        },
-        Patch {
-            shape: vec![0, 1, 0, 0, 1, 0, 1, 1, 1, 0, 1, 0, 0, 1],
-            bt: (1, 4),
-            button: 1,
-        },
-        Patch {
-            shape: vec![1, 1, 0, 0, 1, 0, 0, 1, 0, 1, 1],
-            bt: (1, 5),
-            button: 1,
-        },
-        Patch {
-            shape: vec![1, 1, 0, 0, 1],
-            bt: (1, 3),
-            button: 0,
-        },
-        Patch {
-            shape: vec![1, 0, 0, 1, 0, 0, 1, 0, 0, 1],
-            bt: (3, 3),
-            button: 1,
-        },
-        Patch {
-            shape: vec![1, 0, 0, 1, 0, 0, 1, 1, 0, 0, 1],
-            bt: (2, 3),
-            button: 1,
-        },
-        Patch {
-            shape: vec![0, 1, 1, 1, 1],
-            bt: (3, 2),
-            button: 1,
-        },
-        Patch {
-            shape: vec![1, 1, 1, 1],
-            bt: (4, 2),
-            button: 1,
-        },
-        Patch {
-            shape: vec![1, 1, 1, 1, 0, 1],
-            bt: (1, 2),
-            button: 0,
-        },
-        Patch {
-            shape: vec![1, 1, 0, 0, 1, 1],
-            bt: (7, 6),
-            button: 3,
-        },
-        Patch {
-            shape: vec![0, 1, 0, 1, 1, 1, 1, 1, 1, 0, 1],
-            bt: (5, 3),
-            button: 1,
-        },
-        Patch {
-            shape: vec![1, 1, 0, 1, 1, 0, 1, 0, 0, 1],
-            bt: (10, 5),
-            button: 3,
-        },
-        Patch {
-            shape: vec![1, 0, 0, 1, 1, 1, 1],
-            bt: (5, 5),
-            button: 2,
-        },
-        Patch {
-            shape: vec![1, 0, 0, 1, 0, 0, 1, 0, 0, 1, 0, 0, 1],
-            bt: (7, 1),
-            button: 1,
-        },
-        Patch {
-            shape: vec![1, 1, 1, 0, 1],
-            bt: (2, 2),
-            button: 0,
-        },
-        Patch {
-            shape: vec![1, 1, 0, 1, 1],
-            bt: (6, 5),
-            button: 2,
-        },
-        Patch {
-            shape: vec![1, 1, 1],
-            bt: (2, 2),
-            button: 0,
-        },
-    ];
-    info!("patches len: {}", &patches.len());
-    patches
+// 盖在不能买的patch上的暗色遮罩
+#[derive(Component)]
+pub struct MarketDimMark {
+    pub patch_idx: usize,
 }
 
 pub fn generate_perimeter_positions(n: usize) -> VecDeque<bevy_egui::egui::Vec2> {
@@ -368,13 +76,24 @@ pub fn inner_handle_query_entity_error(e: QueryEntityError) {
 fn on_click_choose_shape(
     click: On<Pointer<Click>>,
     query: Query<&PatchComponent>,
+    board: Res<BoardGameRes>,
+    replay: Option<Res<ReplayRes>>,
+    computer: Option<Res<ComputerPlayer>>,
     mut commands: Commands,
 ) {
+    if replay.is_some() || is_computer_turn(&board, computer.as_deref()) {
+        return;
+    }
     let e = click.event().entity;
     let c = query.get(e);
     match c {
         Ok(pc) => {
             info!("on click choose shape: {:?}", pc.patch_idx);
+            // 只能选中立指示物前面的三块
+            if !board.market_choices().contains(&pc.patch_idx) {
+                warn!("not in market: {}", pc.patch_idx);
+                return;
+            }
             commands.trigger(PatchChoosedEvent {
                 patch_idx: pc.patch_idx,
             });
@@ -431,26 +150,43 @@ fn spawn_patch(
         .id();
     commands.entity(p).add_child(t);
 
+    // 不能买的时候盖上遮罩
+    let d = commands
+        .spawn((
+            Sprite {
+                color: Color::linear_rgba(0., 0., 0., 0.6),
+                custom_size: Some(Vec2::splat(WIDTH_BASE)),
+                ..default()
+            },
+            Transform::from_xyz(0.0, 0.0, 0.3),
+            Visibility::Hidden,
+            MarketDimMark { patch_idx: idx },
+        ))
+        .id();
+    commands.entity(p).add_child(d);
+
     // 在透明Sprite上画形状
-    for (pos, &has) in patch.shape.iter().enumerate() {
-        let row = pos / 3;
-        let col = pos % 3;
-        let color = generate_color(row as i32, col as i32, 5, 3);
+    let shape = &patch.shape;
+    for (col, row) in shape.cells() {
+        let color = generate_color(
+            row as i32,
+            col as i32,
+            shape.height() as i32,
+            shape.width() as i32,
+        );
         let x = col as f32 * square_size + square_size / 2.0 - WIDTH_BASE / 2.0;
         let y = row as f32 * square_size + square_size / 2.0 - WIDTH_BASE / 2.0;
-        if has == 1 {
-            let c = commands
-                .spawn((
-                    Sprite {
-                        color,
-                        custom_size: Some(Vec2::splat(square_size)),
-                        ..default()
-                    },
-                    Transform::from_xyz(x, y, 0.1),
-                ))
-                .id();
-            commands.entity(p).add_child(c);
-        }
+        let c = commands
+            .spawn((
+                Sprite {
+                    color,
+                    custom_size: Some(Vec2::splat(square_size)),
+                    ..default()
+                },
+                Transform::from_xyz(x, y, 0.1),
+            ))
+            .id();
+        commands.entity(p).add_child(c);
     }
 }
 
@@ -478,3 +214,31 @@ pub fn spawn_patches(
         );
     }
 }
+
+// 按中立指示物刷新外圈: 买走的隐藏，买得起又放得下的高亮，其余变暗
+pub fn update_market_view(
+    board: Res<BoardGameRes>,
+    mut patches: Query<(&PatchComponent, &mut Visibility, &mut Sprite)>,
+    mut dims: Query<(&MarketDimMark, &mut Visibility), Without<PatchComponent>>,
+) {
+    let choices = board.buyable_patches();
+    for (pc, mut v, mut sprite) in patches.iter_mut() {
+        *v = if board.patch_pos[pc.patch_idx].is_some() {
+            Visibility::Hidden
+        } else {
+            Visibility::Inherited
+        };
+        sprite.color = if choices.contains(&pc.patch_idx) {
+            Color::linear_rgba(1., 1., 1., 0.15)
+        } else {
+            Color::linear_rgba(0., 0., 0., 0.)
+        };
+    }
+    for (d, mut v) in dims.iter_mut() {
+        *v = if choices.contains(&d.patch_idx) {
+            Visibility::Hidden
+        } else {
+            Visibility::Inherited
+        };
+    }
+}
@@ -0,0 +1,121 @@
+use bevy::prelude::*;
+use bevy_egui::{
+    EguiContexts,
+    egui::{self, Align2, Id},
+};
+use engine_lib::{BoardGame, GameRecord, Patch};
+
+use crate::new_game::game_state::{BoardGameRes, GameState, players_ui};
+
+// 回放模式: 棋谱每一步之后的局面都先算好，前后翻就是换局面
+#[derive(Resource)]
+pub struct ReplayRes {
+    pub record: GameRecord,
+    // 第 0 个是开局
+    pub states: Vec<BoardGame>,
+    // 现在显示的是走了几步之后
+    pub cursor: usize,
+}
+
+impl ReplayRes {
+    pub fn new(record: GameRecord, states: Vec<BoardGame>) -> Self {
+        Self {
+            record,
+            states,
+            cursor: 0,
+        }
+    }
+
+    fn last(&self) -> usize {
+        self.states.len() - 1
+    }
+}
+
+// 开始界面贴进来的棋谱
+#[derive(Resource, Default)]
+pub struct RecordImport {
+    pub text: String,
+    // 上次读棋谱的错误，显示给玩家
+    pub error: Option<String>,
+}
+
+// 读棋谱并按当前拼布目录走一遍，走不通的进游戏之前就报错
+pub fn import_record(text: &str, patches: &[Patch]) -> Result<GameRecord, String> {
+    let record = GameRecord::parse(text).map_err(|e| e.to_string())?;
+    let start = BoardGame::from_catalogue(patches.to_vec(), record.seed);
+    record.replay(start).map_err(|e| e.to_string())?;
+    Ok(record)
+}
+
+// 翻到的局面换进 BoardGameRes，拼布板、外圈都跟着重画
+pub fn sync_replay_board(replay: Res<ReplayRes>, mut board: ResMut<BoardGameRes>) {
+    board.game = replay.states[replay.cursor].clone();
+}
+
+// 左右方向键一步步翻，Home / End 跳到头尾
+pub fn replay_input(keys: Res<ButtonInput<KeyCode>>, mut replay: ResMut<ReplayRes>) {
+    let cursor = if keys.just_pressed(KeyCode::ArrowLeft) {
+        replay.cursor.saturating_sub(1)
+    } else if keys.just_pressed(KeyCode::ArrowRight) {
+        (replay.cursor + 1).min(replay.last())
+    } else if keys.just_pressed(KeyCode::Home) {
+        0
+    } else if keys.just_pressed(KeyCode::End) {
+        replay.last()
+    } else {
+        return;
+    };
+    replay.cursor = cursor;
+}
+
+pub fn replay_ui(
+    mut contexts: EguiContexts,
+    mut replay: ResMut<ReplayRes>,
+    board: Res<BoardGameRes>,
+    mut next_gamestate: ResMut<NextState<GameState>>,
+) -> Result {
+    let ctx = contexts.ctx_mut()?;
+    egui::Area::new(Id::new("replay_ui"))
+        .anchor(Align2::CENTER_TOP, [0.0, 10.0])
+        .show(ctx, |ui| {
+            let r = &replay.record;
+            ui.label(format!("{} vs {}  {}", r.players[0], r.players[1], r.date));
+            if !r.result.is_empty() {
+                ui.label(format!("result: {}", r.result));
+            }
+            players_ui(ui, &board);
+
+            let cursor = replay.cursor;
+            let last = replay.last();
+            let text = match cursor {
+                0 => "start".to_string(),
+                c => replay.record.moves[c - 1].clone(),
+            };
+            ui.label(format!("move {} / {}: {}", cursor, last, text));
+
+            ui.horizontal(|ui| {
+                let mut to = None;
+                if ui.add_enabled(cursor > 0, egui::Button::new("|<")).clicked() {
+                    to = Some(0);
+                }
+                if ui.add_enabled(cursor > 0, egui::Button::new("<")).clicked() {
+                    to = Some(cursor - 1);
+                }
+                if ui.add_enabled(cursor < last, egui::Button::new(">")).clicked() {
+                    to = Some(cursor + 1);
+                }
+                if ui.add_enabled(cursor < last, egui::Button::new(">|")).clicked() {
+                    to = Some(last);
+                }
+                if let Some(to) = to {
+                    replay.cursor = to;
+                }
+            });
+
+            if ui.button("back").clicked() {
+                next_gamestate.set(GameState::HelloUI);
+            }
+        });
+
+    Ok(())
+}
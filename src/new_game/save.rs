@@ -0,0 +1,166 @@
+use bevy::prelude::*;
+use engine_lib::{BoardGame, GameRecord, History, Patch, Snapshot};
+
+use crate::{
+    app::get_local_storage,
+    new_game::{computer::ComputerLevel, game_state::BoardGameRes},
+};
+
+const SAVE_STORAGE_KEY: &str = "patchwork_saved_game";
+// 没下完那局的玩家名字，棋谱对不上只能用存档局面时，靠它认出对手是不是电脑
+const SAVE_PLAYERS_KEY: &str = "patchwork_saved_players";
+// 没下完那局的棋谱，继续的时候用来接上悔棋历史
+const SAVE_RECORD_KEY: &str = "patchwork_saved_record";
+// 上一局下完的棋谱，开始界面可以回放
+const LAST_RECORD_KEY: &str = "patchwork_last_record";
+
+// 开局方式，hello_ui 里选好，进入游戏时 init_game_resource 读
+#[derive(Resource, Default)]
+pub struct GameSetup {
+    // 从 localStorage 的存档继续
+    pub continue_saved: bool,
+    // 回放这份棋谱
+    pub replay: Option<GameRecord>,
+    // 和电脑下，电脑是 1 号
+    pub vs_computer: bool,
+    pub computer_level: ComputerLevel,
+}
+
+// 有没有存档，开始界面决定显不显示 continue
+// 进开始界面的时候读一次，自动存档时更新，不用每帧去读 localStorage
+#[derive(Resource, Default)]
+pub struct HasSavedGame(pub bool);
+
+// 上一局下完的棋谱，进开始界面的时候读一次
+#[derive(Resource, Default)]
+pub struct LastRecord(pub Option<GameRecord>);
+
+// 读回来的存档
+pub struct SavedGame {
+    // 开局的局面，存档没有对得上的棋谱时不知道开局
+    pub start: Option<BoardGame>,
+    pub game: BoardGame,
+    pub history: History,
+    pub players: Option<[String; 2]>,
+}
+
+fn storage_get(key: &str) -> Result<Option<String>, String> {
+    get_local_storage()?
+        .get_item(key)
+        .map_err(|_| format!("读取{}失败", key))
+}
+
+fn storage_set(key: &str, value: &str) -> Result<(), String> {
+    get_local_storage()?
+        .set_item(key, value)
+        .map_err(|_| format!("保存{}到localStorage失败", key))
+}
+
+fn storage_remove(key: &str) -> Result<(), String> {
+    get_local_storage()?
+        .remove_item(key)
+        .map_err(|_| format!("删除{}失败", key))
+}
+
+pub fn refresh_has_saved_game(mut has_saved: ResMut<HasSavedGame>) {
+    has_saved.0 = matches!(storage_get(SAVE_STORAGE_KEY), Ok(Some(_)));
+}
+
+fn load_saved_players() -> Option<[String; 2]> {
+    let json = storage_get(SAVE_PLAYERS_KEY).ok()??;
+    serde_json::from_str(&json).ok()
+}
+
+fn load_last_record() -> Option<GameRecord> {
+    let text = storage_get(LAST_RECORD_KEY).ok()??;
+    GameRecord::parse(&text).ok()
+}
+
+pub fn refresh_last_record(mut last: ResMut<LastRecord>) {
+    last.0 = load_last_record();
+}
+
+// 恢复存档
+// 先按棋谱从开局重走一遍，对得上就连悔棋历史一起接上
+// 棋谱没有或对不上就只用存档的局面，这时没有开局，也就记不了棋谱
+pub fn restore_saved_game(patches: &[Patch]) -> Result<SavedGame, String> {
+    let json = storage_get(SAVE_STORAGE_KEY)?.ok_or("没有存档")?;
+    let snap = Snapshot::from_json(&json).map_err(|e| e.to_string())?;
+
+    // 棋谱读不出来、坏了、格式旧了都只是没有悔棋历史，照样用存档局面继续
+    let text = storage_get(SAVE_RECORD_KEY).unwrap_or_else(|e| {
+        warn!("{}", e);
+        None
+    });
+    if let Some(text) = text {
+        match replay_saved_record(&text, patches, &snap) {
+            Ok(saved) => return Ok(saved),
+            Err(e) => warn!("replay saved record fail: {}", e),
+        }
+    }
+
+    let game = BoardGame::from_snapshot(snap).map_err(|e| e.to_string())?;
+    Ok(SavedGame {
+        start: None,
+        game,
+        history: History::default(),
+        players: load_saved_players(),
+    })
+}
+
+// 按棋谱从开局重走一遍，走到的局面要和存档一样
+fn replay_saved_record(
+    text: &str,
+    patches: &[Patch],
+    snap: &Snapshot,
+) -> Result<SavedGame, String> {
+    let record = GameRecord::parse(text).map_err(|e| e.to_string())?;
+    let start = BoardGame::from_catalogue(patches.to_vec(), record.seed);
+    let mut game = start.clone();
+    let mut history = History::default();
+    for s in record.moves.iter() {
+        let mv = game.parse_move(s).map_err(|e| e.to_string())?;
+        history.play(&mut game, &mv).map_err(|e| format!("{:?}", e))?;
+    }
+    if game.snapshot() != *snap {
+        return Err("saved record does not match the snapshot".to_string());
+    }
+    Ok(SavedGame {
+        start: Some(start),
+        game,
+        history,
+        players: Some(record.players),
+    })
+}
+
+// 每走一步存一次，结束了就删掉存档，棋谱留给回放
+pub fn autosave_game(board: Res<BoardGameRes>, mut has_saved: ResMut<HasSavedGame>) {
+    let record = board.record();
+    let players = serde_json::to_string(&board.player_names).expect("names always serialize");
+    has_saved.0 = !board.is_game_over();
+    let r = if board.is_game_over() {
+        storage_remove(SAVE_STORAGE_KEY)
+            .and_then(|_| storage_remove(SAVE_RECORD_KEY))
+            .and_then(|_| storage_remove(SAVE_PLAYERS_KEY))
+            .and_then(|_| match &record {
+                Some(record) => storage_set(LAST_RECORD_KEY, &record.to_text()),
+                None => Ok(()),
+            })
+    } else {
+        storage_set(SAVE_STORAGE_KEY, &board.snapshot().to_json())
+            .and_then(|_| storage_set(SAVE_PLAYERS_KEY, &players))
+            .and_then(|_| match &record {
+                Some(record) => storage_set(SAVE_RECORD_KEY, &record.to_text()),
+                None => storage_remove(SAVE_RECORD_KEY),
+            })
+    };
+    if let Err(e) = r {
+        warn!("autosave fail: {}", e);
+    }
+}
+
+// 今天的日期，写进棋谱
+pub fn today() -> String {
+    let iso = String::from(js_sys::Date::new_0().to_iso_string());
+    iso.chars().take(10).collect()
+}
@@ -0,0 +1,299 @@
+// 电脑自己跟自己下: 两个电脑按不同 seed 下 N 局，统计胜率、分数分布、拼布购买频率
+// 调估值权重、改拼布目录之后用来看效果
+//
+// tournament [options] <bot a> <bot b>
+//   bot: random | greedy | mcts-easy | mcts-normal | mcts-hard
+
+mod report;
+
+use std::{
+    env, fs,
+    num::NonZeroUsize,
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+
+use engine_lib::{
+    BoardGame, Bot, Difficulty, GameRng, GreedyBot, MctsBot, Move, Patch, load_catalogue,
+    new_patches,
+};
+
+use report::{GameResult, Report};
+
+const USAGE: &str = "usage: tournament [options] <bot a> <bot b>
+
+bots: random, greedy, mcts-easy, mcts-normal, mcts-hard
+
+options:
+  --games <n>         number of games, default 100
+  --seed <n>          seed of the first game, game i uses seed + i, default 0
+  --threads <n>       worker threads, default all cores
+  --catalogue <path>  patch catalogue json, default the built-in one
+  --format json|csv   report format, default json
+  --out <path>        write the report to a file instead of stdout";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum BotKind {
+    Random,
+    Greedy,
+    Mcts(Difficulty),
+}
+
+impl BotKind {
+    fn parse(name: &str) -> Option<BotKind> {
+        Some(match name {
+            "random" => BotKind::Random,
+            "greedy" => BotKind::Greedy,
+            "mcts-easy" => BotKind::Mcts(Difficulty::Easy),
+            "mcts-normal" => BotKind::Mcts(Difficulty::Normal),
+            "mcts-hard" => BotKind::Mcts(Difficulty::Hard),
+            _ => return None,
+        })
+    }
+
+    // 只按次数搜索，不设时间限制，同一个 seed 结果可复现
+    fn build(self, seed: u64) -> Box<dyn Bot> {
+        match self {
+            BotKind::Random => Box::new(RandomBot {
+                rng: GameRng::new(seed),
+            }),
+            BotKind::Greedy => Box::new(GreedyBot::default()),
+            BotKind::Mcts(difficulty) => {
+                let mut config = difficulty.config();
+                config.time_limit_ms = None;
+                config.seed = seed;
+                Box::new(MctsBot::with_config(config))
+            }
+        }
+    }
+}
+
+// 随便走，当基准线
+struct RandomBot {
+    rng: GameRng,
+}
+
+impl Bot for RandomBot {
+    fn name(&self) -> &str {
+        "random"
+    }
+
+    fn choose(&mut self, game: &BoardGame) -> Option<Move> {
+        let moves = game.legal_moves();
+        if moves.is_empty() {
+            return None;
+        }
+        Some(moves[self.rng.below(moves.len())])
+    }
+}
+
+#[derive(Debug)]
+struct Options {
+    bots: [BotKind; 2],
+    names: [String; 2],
+    games: usize,
+    seed: u64,
+    threads: usize,
+    catalogue: Option<String>,
+    csv: bool,
+    out: Option<String>,
+}
+
+fn parse_args(args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut games = 100;
+    let mut seed = 0;
+    let mut threads = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+    let mut catalogue = None;
+    let mut csv = false;
+    let mut out = None;
+    let mut names = vec![];
+
+    let mut args = args.peekable();
+    while let Some(arg) = args.next() {
+        if !arg.starts_with("--") {
+            names.push(arg);
+            continue;
+        }
+        let value = args.next().ok_or(format!("missing value for {}", arg))?;
+        let number = |v: &str| {
+            v.parse::<u64>()
+                .map_err(|_| format!("bad number for {}: {}", arg, v))
+        };
+        match arg.as_str() {
+            "--games" => games = number(&value)? as usize,
+            "--seed" => seed = number(&value)?,
+            "--threads" => threads = (number(&value)? as usize).max(1),
+            "--catalogue" => catalogue = Some(value),
+            "--format" => match value.as_str() {
+                "json" => csv = false,
+                "csv" => csv = true,
+                _ => return Err(format!("unknown format: {}", value)),
+            },
+            "--out" => out = Some(value),
+            _ => return Err(format!("unknown option: {}", arg)),
+        }
+    }
+
+    let [a, b]: [String; 2] = names
+        .try_into()
+        .map_err(|_| "need exactly two bots".to_string())?;
+    let kind = |name: &str| BotKind::parse(name).ok_or(format!("unknown bot: {}", name));
+    Ok(Options {
+        bots: [kind(&a)?, kind(&b)?],
+        names: [a, b],
+        games,
+        seed,
+        threads,
+        catalogue,
+        csv,
+        out,
+    })
+}
+
+// 第 index 局: 单数局换先手，两边先后手一样多
+fn play_game(patches: &[Patch], bots: [BotKind; 2], seed: u64, index: usize) -> GameResult {
+    let seed = seed.wrapping_add(index as u64);
+    let first = index % 2;
+    // 座位 -> 电脑编号
+    let seats = [first, 1 - first];
+    let mut players = seats.map(|b| bots[b].build(seed.wrapping_mul(2).wrapping_add(b as u64)));
+
+    let mut game = BoardGame::from_catalogue(patches.to_vec(), seed);
+    let mut plies = 0;
+    while let Some(mv) = players[game.current_player()].choose(&game) {
+        if let Err(e) = game.play(&mv) {
+            panic!(
+                "seed {}: {} chose an illegal move {:?}: {:?}",
+                seed,
+                players[game.current_player()].name(),
+                mv,
+                e
+            );
+        }
+        plies += 1;
+    }
+    // 电脑给不出行动只应该是下完了，否则是规则的 bug，报出 seed 方便复现
+    assert!(game.is_game_over(), "seed {}: no legal move but game not over", seed);
+
+    let mut bought = [vec![], vec![]];
+    for (idx, pos) in game.patch_pos.iter().enumerate() {
+        if let Some(pos) = pos {
+            bought[seats[pos.player]].push(game.patches[idx].id);
+        }
+    }
+    let mut scores = [0; 2];
+    for (seat, &b) in seats.iter().enumerate() {
+        scores[b] = game.score(seat).total();
+    }
+    GameResult {
+        seed,
+        first,
+        scores,
+        winner: seats[game.winner().expect("game over always has a winner")],
+        plies,
+        bought,
+    }
+}
+
+// 几个线程轮流领下一局
+fn run(patches: &[Patch], opts: &Options) -> Vec<GameResult> {
+    let next = AtomicUsize::new(0);
+    let done = AtomicUsize::new(0);
+    let mut results: Vec<(usize, GameResult)> = thread::scope(|s| {
+        let workers: Vec<_> = (0..opts.threads.min(opts.games.max(1)))
+            .map(|_| {
+                s.spawn(|| {
+                    let mut results = vec![];
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        if index >= opts.games {
+                            break;
+                        }
+                        results.push((index, play_game(patches, opts.bots, opts.seed, index)));
+                        let n = done.fetch_add(1, Ordering::Relaxed) + 1;
+                        if n.is_multiple_of((opts.games / 10).max(1)) {
+                            eprintln!("{}/{} games", n, opts.games);
+                        }
+                    }
+                    results
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|w| w.join().expect("worker panicked"))
+            .collect()
+    });
+    results.sort_by_key(|&(index, _)| index);
+    results.into_iter().map(|(_, r)| r).collect()
+}
+
+fn main() {
+    let opts = match parse_args(env::args().skip(1)) {
+        Ok(opts) => opts,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            process::exit(2);
+        }
+    };
+    let patches = match &opts.catalogue {
+        Some(path) => load_catalogue(path).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(1);
+        }),
+        None => new_patches(),
+    };
+
+    let results = run(&patches, &opts);
+    let ids: Vec<usize> = patches.iter().map(|p| p.id).collect();
+    let report = Report::new(&opts.names, &ids, results);
+    let text = if opts.csv {
+        report.to_csv()
+    } else {
+        report.to_json()
+    };
+    match &opts.out {
+        Some(path) => {
+            if let Err(e) = fs::write(path, text) {
+                eprintln!("write {} fail: {}", path, e);
+                process::exit(1);
+            }
+        }
+        None => println!("{}", text),
+    }
+}
+
+#[test]
+fn test_tournament() {
+    let args = [
+        "--games",
+        "4",
+        "--threads",
+        "2",
+        "--format",
+        "csv",
+        "random",
+        "greedy",
+    ];
+    let opts = parse_args(args.iter().map(|s| s.to_string())).unwrap();
+    assert_eq!(opts.bots, [BotKind::Random, BotKind::Greedy]);
+    assert!(opts.csv);
+    assert!(parse_args(["greedy"].iter().map(|s| s.to_string())).is_err());
+    assert!(parse_args(["greedy", "nobody"].iter().map(|s| s.to_string())).is_err());
+
+    // 同样的参数结果一样，和线程数无关
+    let patches = new_patches();
+    let results = run(&patches, &opts);
+    assert_eq!(results.len(), 4);
+    assert_eq!(
+        results.iter().map(|r| r.first).collect::<Vec<_>>(),
+        vec![0, 1, 0, 1]
+    );
+    let single = run(&patches, &Options { threads: 1, ..opts });
+    let scores = |rs: &[GameResult]| rs.iter().map(|r| r.scores).collect::<Vec<_>>();
+    assert_eq!(scores(&results), scores(&single));
+    for r in results.iter() {
+        assert!(r.scores[r.winner] >= r.scores[1 - r.winner]);
+    }
+}
@@ -0,0 +1,227 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+// 一局的结果，下标都是参赛电脑的编号 (命令行里的顺序)，不是座位
+#[derive(Clone, Serialize, Debug)]
+pub struct GameResult {
+    pub seed: u64,
+    // 先手 (0 号座位) 的电脑
+    pub first: usize,
+    pub scores: [isize; 2],
+    pub winner: usize,
+    pub plies: usize,
+    // 各自买下的拼布编号
+    pub bought: [Vec<usize>; 2],
+}
+
+#[derive(Serialize, Debug)]
+pub struct BotStats {
+    pub name: String,
+    pub games: usize,
+    pub wins: usize,
+    pub win_rate: f64,
+    // 先手的局数和先手赢的局数
+    pub first_games: usize,
+    pub first_wins: usize,
+    pub avg_score: f64,
+    pub stddev: f64,
+    pub min_score: isize,
+    pub max_score: isize,
+    // 分数 -> 局数
+    pub scores: BTreeMap<isize, usize>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PatchStats {
+    pub id: usize,
+    // 被买了几次，每局最多一次
+    pub bought: usize,
+    pub buy_rate: f64,
+    // 两个电脑各买了几次
+    pub by: [usize; 2],
+    // 买了它的一方最后赢的比例，没人买过是 None
+    pub buyer_win_rate: Option<f64>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Report {
+    pub games: usize,
+    pub bots: Vec<BotStats>,
+    pub patches: Vec<PatchStats>,
+    pub results: Vec<GameResult>,
+}
+
+impl Report {
+    // patch_ids: 拼布目录里所有的编号，没人买过的也要列出来
+    pub fn new(names: &[String; 2], patch_ids: &[usize], results: Vec<GameResult>) -> Self {
+        let games = results.len();
+        let bots = (0..2)
+            .map(|b| {
+                let scores: Vec<isize> = results.iter().map(|r| r.scores[b]).collect();
+                let wins = results.iter().filter(|r| r.winner == b).count();
+                let first_games = results.iter().filter(|r| r.first == b).count();
+                let first_wins = results
+                    .iter()
+                    .filter(|r| r.first == b && r.winner == b)
+                    .count();
+                let avg = mean(scores.iter().map(|&s| s as f64));
+                let var = mean(scores.iter().map(|&s| (s as f64 - avg).powi(2)));
+                let mut dist = BTreeMap::new();
+                for &s in scores.iter() {
+                    *dist.entry(s).or_insert(0) += 1;
+                }
+                BotStats {
+                    name: names[b].clone(),
+                    games,
+                    wins,
+                    win_rate: ratio(wins, games),
+                    first_games,
+                    first_wins,
+                    avg_score: avg,
+                    stddev: var.sqrt(),
+                    min_score: scores.iter().copied().min().unwrap_or(0),
+                    max_score: scores.iter().copied().max().unwrap_or(0),
+                    scores: dist,
+                }
+            })
+            .collect();
+
+        let mut ids = patch_ids.to_vec();
+        ids.sort_unstable();
+        ids.dedup();
+        let patches = ids
+            .into_iter()
+            .map(|id| {
+                let mut by = [0; 2];
+                let mut buyer_wins = 0;
+                for r in results.iter() {
+                    for (b, count) in by.iter_mut().enumerate() {
+                        if r.bought[b].contains(&id) {
+                            *count += 1;
+                            if r.winner == b {
+                                buyer_wins += 1;
+                            }
+                        }
+                    }
+                }
+                let bought = by[0] + by[1];
+                PatchStats {
+                    id,
+                    bought,
+                    buy_rate: ratio(bought, games),
+                    by,
+                    buyer_win_rate: (bought > 0).then(|| ratio(buyer_wins, bought)),
+                }
+            })
+            .collect();
+
+        Self {
+            games,
+            bots,
+            patches,
+            results,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("report is always serializable")
+    }
+
+    // 三张表: 电脑汇总、分数分布、拼布购买，中间空一行
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from(
+            "bot,games,wins,win_rate,first_games,first_wins,avg_score,stddev,min_score,max_score\n",
+        );
+        for b in self.bots.iter() {
+            out += &format!(
+                "{},{},{},{:.4},{},{},{:.3},{:.3},{},{}\n",
+                b.name,
+                b.games,
+                b.wins,
+                b.win_rate,
+                b.first_games,
+                b.first_wins,
+                b.avg_score,
+                b.stddev,
+                b.min_score,
+                b.max_score
+            );
+        }
+
+        out += "\nbot,score,count\n";
+        for b in self.bots.iter() {
+            for (score, count) in b.scores.iter() {
+                out += &format!("{},{},{}\n", b.name, score, count);
+            }
+        }
+
+        out += "\npatch,bought,buy_rate,bought_by_a,bought_by_b,buyer_win_rate\n";
+        for p in self.patches.iter() {
+            let buyer_win_rate = p
+                .buyer_win_rate
+                .map(|r| format!("{:.4}", r))
+                .unwrap_or_default();
+            out += &format!(
+                "{},{},{:.4},{},{},{}\n",
+                p.id, p.bought, p.buy_rate, p.by[0], p.by[1], buyer_win_rate
+            );
+        }
+        out
+    }
+}
+
+fn mean(values: impl Iterator<Item = f64>) -> f64 {
+    let (sum, n) = values.fold((0.0, 0), |(sum, n), v| (sum + v, n + 1));
+    if n == 0 { 0.0 } else { sum / n as f64 }
+}
+
+fn ratio(a: usize, b: usize) -> f64 {
+    if b == 0 { 0.0 } else { a as f64 / b as f64 }
+}
+
+#[test]
+fn test_report() {
+    let names = ["greedy".to_string(), "random".to_string()];
+    let results = vec![
+        GameResult {
+            seed: 0,
+            first: 0,
+            scores: [10, -20],
+            winner: 0,
+            plies: 40,
+            bought: [vec![1, 2], vec![3]],
+        },
+        GameResult {
+            seed: 1,
+            first: 1,
+            scores: [4, 6],
+            winner: 1,
+            plies: 42,
+            bought: [vec![2], vec![1]],
+        },
+    ];
+    let report = Report::new(&names, &[3, 2, 1, 0], results);
+
+    let a = &report.bots[0];
+    assert_eq!((a.wins, a.first_games, a.first_wins), (1, 1, 1));
+    assert_eq!(a.avg_score, 7.0);
+    assert_eq!(a.stddev, 3.0);
+    assert_eq!((a.min_score, a.max_score), (4, 10));
+    assert_eq!(report.bots[1].scores.get(&-20), Some(&1));
+
+    // 没人买过的 0 号也在
+    let ids: Vec<usize> = report.patches.iter().map(|p| p.id).collect();
+    assert_eq!(ids, vec![0, 1, 2, 3]);
+    assert_eq!(report.patches[0].buyer_win_rate, None);
+    let p1 = &report.patches[1];
+    assert_eq!((p1.bought, p1.by), (2, [1, 1]));
+    assert_eq!(p1.buyer_win_rate, Some(1.0));
+    assert_eq!(report.patches[2].buyer_win_rate, Some(0.5));
+
+    let csv = report.to_csv();
+    assert!(csv.starts_with("bot,games,wins"));
+    assert!(!csv.contains("\ngreedy,-20,"));
+    assert!(csv.contains("\nrandom,-20,1\n"));
+    assert!(report.to_json().contains("\"buy_rate\""));
+}